@@ -0,0 +1,79 @@
+use criterion::{
+	black_box,
+	criterion_group,
+	criterion_main,
+	Criterion,
+};
+use erc20::{
+	erc20::{
+		ContractAddress,
+		ERC20Method,
+	},
+	transaction::TransactionAndTransferType,
+	ERC20Error,
+};
+use std::{
+	convert::TryInto,
+	str::FromStr,
+};
+use web3::types::{
+	Bytes,
+	H160,
+	H256,
+	Transaction,
+};
+
+const TRANSFER_INPUT: &str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
+
+fn erc20_method_from(c: &mut Criterion) {
+	let inputs: Vec<Vec<u8>> = (0..10_000u32).map(|i| {
+		let mut input = hex::decode(TRANSFER_INPUT).unwrap();
+		// Every other input is not an ERC20 method.
+		if i % 2 == 0 {
+			input[0] = 0;
+		}
+		input
+	}).collect();
+
+	c.bench_function("ERC20Method::from 10k inputs", |b| b.iter(|| {
+		for input in inputs.iter() {
+			let _method: ERC20Method = black_box(input.clone()).into();
+		}
+	}));
+}
+
+fn transaction_try_from(c: &mut Criterion) {
+	let transaction = Transaction {
+		hash: H256::from_str("43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee").unwrap(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(ContractAddress::USDC.into()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(TRANSFER_INPUT).unwrap()),
+		raw: None,
+	};
+
+	c.bench_function("TransactionAndTransferType::try_from", |b| b.iter(|| {
+		let _resp: Result<TransactionAndTransferType, ERC20Error> = black_box(transaction.clone()).try_into();
+	}));
+}
+
+fn contract_address_from(c: &mut Criterion) {
+	let usdc_address: H160 = ContractAddress::USDC.into();
+	let unknown_address = H160::random();
+
+	c.bench_function("ContractAddress::from known", |b| b.iter(|| {
+		let _contract: ContractAddress = black_box(usdc_address).into();
+	}));
+	c.bench_function("ContractAddress::from unidentified", |b| b.iter(|| {
+		let _contract: ContractAddress = black_box(unknown_address).into();
+	}));
+}
+
+criterion_group!(benches, erc20_method_from, transaction_try_from, contract_address_from);
+criterion_main!(benches);
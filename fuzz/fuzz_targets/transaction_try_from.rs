@@ -0,0 +1,38 @@
+#![no_main]
+use erc20::{
+	transaction::TransactionAndTransferType,
+	transfer::Transfer,
+	ERC20Error,
+};
+use libfuzzer_sys::fuzz_target;
+use std::convert::TryInto;
+use web3::types::{
+	Bytes,
+	H160,
+	Transaction,
+};
+
+fuzz_target!(|data: &[u8]| {
+	let transaction = Transaction {
+		hash: Default::default(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::from_low_u64_be(1),
+		to: Some(H160::from_low_u64_be(2)),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(data.to_vec()),
+		raw: None,
+	};
+
+	let resp: Result<TransactionAndTransferType, ERC20Error> = transaction.try_into();
+	if let Ok(transfer) = resp {
+		let _from = transfer.from();
+		let _to = transfer.to();
+		let _value = transfer.value();
+		let _contract = transfer.contract();
+	}
+});
@@ -1,20 +1,45 @@
 ///! ERC20 specific information.
 
-use crate::ERC20Error;
-use maplit::hashmap;
+use crate::{
+	util::{
+		keccak256,
+		BytesToFixedNumber,
+		WORD_SIZE,
+	},
+	ERC20Error,
+};
+use lazy_static::lazy_static;
+use maplit::{
+	hashmap,
+	hashset,
+};
+use serde_json::json;
 use serde::{
 	Deserialize,
 	Serialize,
 };
 use std::{
-	collections::HashMap,
+	cmp::Ordering,
+	collections::{
+		HashMap,
+		HashSet,
+	},
 	convert::{
 		TryFrom,
 		TryInto,
 	},
 	str::FromStr,
+	sync::{
+		PoisonError,
+		RwLock,
+	},
+};
+use web3::types::{
+	H160,
+	H256,
+	Log,
+	U256,
 };
-use web3::types::H160;
 
 /// ERC20 method operation
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -32,23 +57,19 @@ pub enum ERC20Method {
 	Transfer,
 	/// Transfers `value` amount of tokens from address `from` to address `to`, and MUST fire the Transfer event.
 	TransferFrom,
+	/// ERC-777 `send`, transfers `amount` of tokens to address `to` along with some `data` for the recipient.
+	Send,
 	/// In case it is not identified an ERC20 operation.
 	Unidentified,
+	/// In case it is not identified an ERC20 operation, keeping the observed selector.
+	UnidentifiedWith([u8; 4]),
 }
 
 impl TryFrom<ERC20Method> for [u8; 4] {
 	type Error = ERC20Error;
 
 	fn try_from(value: ERC20Method) -> Result<Self, Self::Error> {
-		match value {
-			ERC20Method::Allowance => Ok([0xdd, 0x62, 0xed, 0x3e]),
-			ERC20Method::Approve => Ok([0x09, 0x5e, 0xa7, 0xb3]),
-			ERC20Method::BalanceOf => Ok([0x70, 0xa0, 0x82, 0x31]),
-			ERC20Method::TotalSupply => Ok([0x18, 0x16, 0x0d, 0xdd]),
-			ERC20Method::Transfer => Ok([0xa9, 0x05, 0x9c, 0xbb]),
-			ERC20Method::TransferFrom => Ok([0x23, 0xb8, 0x72, 0xdd]),
-			ERC20Method::Unidentified => Err(ERC20Error::UnexpectedType),
-		}
+		value.to_selector().ok_or(ERC20Error::UnexpectedType)
 	}
 }
 
@@ -74,13 +95,179 @@ impl From<Vec<u8>> for ERC20Method {
 				Self::TotalSupply => Self::TotalSupply.try_into().unwrap(),
 				Self::Transfer => Self::Transfer.try_into().unwrap(),
 				Self::TransferFrom => Self::TransferFrom.try_into().unwrap(),
+				Self::Send => Self::Send.try_into().unwrap(),
 			};
 			for (key, value) in method_encoding {
 				if data.starts_with(&value) {
 					return key;
 				}
 			}
-			Self::Unidentified
+			let mut selector = [0; 4];
+			selector.clone_from_slice(&data[..4]);
+			Self::UnidentifiedWith(selector)
+		}
+	}
+}
+
+impl ERC20Method {
+	/// Returns the method selector, `None` for `Unidentified`, usable in const contexts.
+	///
+	/// `Option::unwrap` is not const, so the selector is extracted with a `match`:
+	///
+	/// ```
+	/// use erc20::erc20::ERC20Method;
+	///
+	/// const TRANSFER: [u8; 4] = match ERC20Method::Transfer.to_selector() {
+	/// 	Some(selector) => selector,
+	/// 	None => [0; 4],
+	/// };
+	/// assert_eq!([0xa9, 0x05, 0x9c, 0xbb], TRANSFER);
+	/// ```
+	// Taking `self` by value keeps it callable on the variants in const contexts.
+	#[allow(clippy::wrong_self_convention)]
+	pub const fn to_selector(self) -> Option<[u8; 4]> {
+		match self {
+			Self::Allowance => Some([0xdd, 0x62, 0xed, 0x3e]),
+			Self::Approve => Some([0x09, 0x5e, 0xa7, 0xb3]),
+			Self::BalanceOf => Some([0x70, 0xa0, 0x82, 0x31]),
+			Self::TotalSupply => Some([0x18, 0x16, 0x0d, 0xdd]),
+			Self::Transfer => Some([0xa9, 0x05, 0x9c, 0xbb]),
+			Self::TransferFrom => Some([0x23, 0xb8, 0x72, 0xdd]),
+			Self::Send => Some([0x9b, 0xd9, 0xbb, 0xc6]),
+			Self::Unidentified => None,
+			Self::UnidentifiedWith(selector) => Some(selector),
+		}
+	}
+
+	/// Returns all the identified methods, including the ERC-777 `Send`.
+	pub fn all() -> &'static [ERC20Method] {
+		&[
+			Self::Allowance, Self::Approve, Self::BalanceOf, Self::TotalSupply, Self::Transfer, Self::TransferFrom,
+			Self::Send,
+		]
+	}
+
+	/// Returns the canonical signature hashed into the selector, `None` for the unidentified ones.
+	pub fn signature(&self) -> Option<&'static str> {
+		match self {
+			Self::Allowance => Some("allowance(address,address)"),
+			Self::Approve => Some("approve(address,uint256)"),
+			Self::BalanceOf => Some("balanceOf(address)"),
+			Self::TotalSupply => Some("totalSupply()"),
+			Self::Transfer => Some("transfer(address,uint256)"),
+			Self::TransferFrom => Some("transferFrom(address,address,uint256)"),
+			Self::Send => Some("send(address,uint256,bytes)"),
+			Self::Unidentified | Self::UnidentifiedWith(_) => None,
+		}
+	}
+
+	/// Returns the JSON ABI entry for the method, `None` for the unidentified ones.
+	///
+	/// ```
+	/// use erc20::erc20::ERC20Method;
+	///
+	/// let fragment = ERC20Method::Transfer.abi_fragment().unwrap();
+	/// assert_eq!("transfer", fragment["name"]);
+	/// assert_eq!("_to", fragment["inputs"][0]["name"]);
+	/// assert_eq!("uint256", fragment["inputs"][1]["type"]);
+	/// ```
+	pub fn abi_fragment(&self) -> Option<serde_json::Value> {
+		let (name, inputs, outputs, state_mutability) = match self {
+			Self::Allowance => (
+				"allowance", vec![("_owner", "address"), ("_spender", "address")], vec![("remaining", "uint256")], "view",
+			),
+			Self::Approve => (
+				"approve", vec![("_spender", "address"), ("_value", "uint256")], vec![("success", "bool")], "nonpayable",
+			),
+			Self::BalanceOf => ("balanceOf", vec![("_owner", "address")], vec![("balance", "uint256")], "view"),
+			Self::TotalSupply => ("totalSupply", vec![], vec![("", "uint256")], "view"),
+			Self::Transfer => (
+				"transfer", vec![("_to", "address"), ("_value", "uint256")], vec![("success", "bool")], "nonpayable",
+			),
+			Self::TransferFrom => (
+				"transferFrom", vec![("_from", "address"), ("_to", "address"), ("_value", "uint256")],
+				vec![("success", "bool")], "nonpayable",
+			),
+			Self::Send => (
+				"send", vec![("to", "address"), ("amount", "uint256"), ("data", "bytes")], vec![], "nonpayable",
+			),
+			Self::Unidentified | Self::UnidentifiedWith(_) => return None,
+		};
+		let to_json = |params: Vec<(&str, &str)>| -> Vec<serde_json::Value> {
+			params.into_iter().map(|(name, the_type)| json!({"name": name, "type": the_type})).collect()
+		};
+		Some(json!({
+			"type": "function",
+			"name": name,
+			"inputs": to_json(inputs),
+			"outputs": to_json(outputs),
+			"stateMutability": state_mutability
+		}))
+	}
+}
+
+/// Description of a method registered in a `SelectorTable`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectorDescriptor {
+	/// Method name.
+	pub name: String,
+}
+
+/// User supplied table of method selectors.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectorTable {
+	entries: Vec<([u8; 4], SelectorDescriptor)>,
+}
+
+impl SelectorTable {
+	/// Creates a table with the ERC20 methods registered.
+	pub fn with_erc20() -> Self {
+		let mut resp: Self = Default::default();
+		for method in ERC20Method::all() {
+			let name = method.abi_fragment().unwrap()["name"].as_str().unwrap().to_string();
+			resp.register(method.clone().try_into().unwrap(), SelectorDescriptor { name });
+		}
+		resp
+	}
+
+	/// Registers a method for the selector.
+	///
+	/// # Arguments
+	///
+	/// * `selector` - The 4 bytes method selector.
+	/// * `descriptor` - Description of the method.
+	///
+	pub fn register(&mut self, selector: [u8; 4], descriptor: SelectorDescriptor) {
+		self.entries.push((selector, descriptor));
+	}
+
+	/// Returns the first method registered for the selector.
+	///
+	/// # Arguments
+	///
+	/// * `selector` - The 4 bytes method selector.
+	///
+	pub fn get(&self, selector: [u8; 4]) -> Option<&SelectorDescriptor> {
+		self.entries.iter().find(|(it, _)| *it == selector).map(|(_, descriptor)| descriptor)
+	}
+
+	/// Checks that no selector was registered more than once, returning the colliding selectors.
+	///
+	/// Registering the same method twice is reported as well, since it is likely an accidental duplicate.
+	pub fn check_collisions(&self) -> Result<(), Vec<[u8; 4]>> {
+		let mut resp: Vec<[u8; 4]> = Vec::new();
+		for (i, (selector, _)) in self.entries.iter().enumerate() {
+			let collides = self.entries[..i].iter().any(|(it, _)| it == selector);
+			if collides && !resp.contains(selector) {
+				resp.push(*selector);
+			}
+		}
+		if resp.is_empty() {
+			Ok(())
+		} else {
+			Err(resp)
 		}
 	}
 }
@@ -100,7 +287,7 @@ impl From<Vec<u8>> for ERC20Method {
 /// assert_eq!(ContractAddress::TUSD, contract_address);
 ///
 /// // Getting the address H160 from the `ContractAddress`.
-/// let usdc_address: web3::types::H160 = crate::erc20::erc20::ContractAddress::USDC.into();
+/// let usdc_address: web3::types::H160 = crate::erc20::erc20::ContractAddress::USDC.try_address().unwrap();
 /// assert_eq!("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", format!("{:?}", usdc_address));
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -144,7 +331,48 @@ pub enum ContractAddress {
 }
 
 impl ContractAddress {
-	fn contract_and_address() -> HashMap<ContractAddress, H160> {
+	/// Converts an `Unidentified` with a known address into the proper variant.
+	///
+	/// `Unidentified(address)` and the variant for the same address are not equal, this makes them comparable.
+	pub fn canonicalize(self) -> ContractAddress {
+		match self {
+			ContractAddress::Unidentified(address) => address.into(),
+			_ => self,
+		}
+	}
+
+	/// Returns the token symbol, `None` for `Unidentified`.
+	pub fn symbol(&self) -> Option<&'static str> {
+		match self {
+			ContractAddress::BAT => Some("BAT"),
+			ContractAddress::BNB => Some("BNB"),
+			ContractAddress::BUSD => Some("BUSD"),
+			ContractAddress::LINK => Some("LINK"),
+			ContractAddress::TUSD => Some("TUSD"),
+			ContractAddress::USDC => Some("USDC"),
+			ContractAddress::USDT => Some("USDT"),
+			ContractAddress::WBTC => Some("WBTC"),
+			ContractAddress::cDAI => Some("cDAI"),
+			ContractAddress::CRO => Some("CRO"),
+			ContractAddress::OKB => Some("OKB"),
+			ContractAddress::LEO => Some("LEO"),
+			ContractAddress::WFIL => Some("WFIL"),
+			ContractAddress::VEN => Some("VEN"),
+			ContractAddress::DAI => Some("DAI"),
+			ContractAddress::UNI => Some("UNI"),
+			ContractAddress::Unidentified(_) => None,
+		}
+	}
+
+	/// Returns the contract address, or `UnexpectedType` if it is not known.
+	pub fn try_address(&self) -> Result<H160, ERC20Error> {
+		match self {
+			ContractAddress::Unidentified(address) => Ok(*address),
+			_ => Self::contract_and_address().remove(self).ok_or(ERC20Error::UnexpectedType),
+		}
+	}
+
+	pub(crate) fn contract_and_address() -> HashMap<ContractAddress, H160> {
 		hashmap! {
 			ContractAddress::TUSD => H160::from_str("0000000000085d4780B73119b644AE5ecd22b376").unwrap(),
 			ContractAddress::LINK => H160::from_str("514910771af9ca656af840dff83e8264ecf986ca").unwrap(),
@@ -177,22 +405,291 @@ impl From<H160> for ContractAddress {
 	}
 }
 
+/// Panics if the contract has no address, not available with the `strict` feature in favor of `try_address`.
+#[cfg(not(feature = "strict"))]
 impl From<ContractAddress> for H160 {
 	fn from(contract_address: ContractAddress) -> Self {
-		for (contract, address) in ContractAddress::contract_and_address() {
-			if contract_address == contract {
-				return address;
+		match contract_address.try_address() {
+			Ok(address) => address,
+			Err(_) => panic!("Unexpected contract {:?}", contract_address),
+		}
+	}
+}
+
+impl TryFrom<&str> for ContractAddress {
+	type Error = ERC20Error;
+
+	/// Parses the token symbol, case insensitive.
+	fn try_from(value: &str) -> Result<Self, Self::Error> {
+		let value = value.to_uppercase();
+		for (contract, _) in Self::contract_and_address() {
+			if contract.symbol().map_or(false, |it| it.to_uppercase() == value) {
+				return Ok(contract);
 			}
 		}
-		match contract_address {
-			ContractAddress::Unidentified(address) => address,
-			_ => panic!("Unexpected contract {:?}", contract_address),
+		Err(ERC20Error::UnexpectedType)
+	}
+}
+
+impl Ord for ContractAddress {
+	/// Orders the known tokens by symbol alphabetically, ignoring the case, followed by the `Unidentified` ones by address.
+	fn cmp(&self, other: &Self) -> Ordering {
+		match (self, other) {
+			(ContractAddress::Unidentified(a), ContractAddress::Unidentified(b)) => a.cmp(b),
+			(ContractAddress::Unidentified(_), _) => Ordering::Greater,
+			(_, ContractAddress::Unidentified(_)) => Ordering::Less,
+			_ => {
+				let a = self.symbol().unwrap_or_default().bytes().map(|it| it.to_ascii_lowercase());
+				let b = other.symbol().unwrap_or_default().bytes().map(|it| it.to_ascii_lowercase());
+				a.cmp(b)
+			}
 		}
 	}
 }
 
+impl PartialOrd for ContractAddress {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
 impl Default for ContractAddress {
 	fn default() -> Self {
 		From::<H160>::from(Default::default())
 	}
 }
+
+/// Default tokens charging a fee on transfers, so the recipient gets less than the transfer value.
+///
+/// It only holds a well-known example, Paxos Gold charging a fee on every transfer,
+/// the others, like the reflection tokens, must be added with `register_fee_on_transfer`.
+fn fee_on_transfer_tokens() -> HashSet<H160> {
+	hashset! {
+		// Paxos Gold
+		H160::from_str("45804880De22913dAFE09f4980848ECE6EcbAf78").unwrap(),
+	}
+}
+
+lazy_static! {
+	static ref FEE_ON_TRANSFER_TOKENS: RwLock<HashSet<H160>> = RwLock::new(fee_on_transfer_tokens());
+	static ref ALL_SELECTORS: Vec<[u8; 4]> = ERC20Method::all().iter()
+		.map(|method| method.clone().try_into().unwrap())
+		.collect();
+}
+
+/// Returns the selectors of all the identified methods, in the same order as `ERC20Method::all`.
+pub fn all_selectors() -> &'static [[u8; 4]] {
+	&ALL_SELECTORS
+}
+
+/// Registers a token charging a fee on transfers, in addition to the default ones.
+///
+/// # Arguments
+///
+/// * `addr` - Address of the token contract.
+///
+pub fn register_fee_on_transfer(addr: H160) {
+	FEE_ON_TRANSFER_TOKENS.write().unwrap_or_else(PoisonError::into_inner).insert(addr);
+}
+
+/// Checks if the token charges a fee on transfers, either default or registered.
+///
+/// # Arguments
+///
+/// * `addr` - Address of the token contract.
+///
+pub fn is_fee_on_transfer(addr: H160) -> bool {
+	FEE_ON_TRANSFER_TOKENS.read().unwrap_or_else(PoisonError::into_inner).contains(&addr)
+}
+
+/// Topic for the `Transfer(address,address,uint256)` event.
+pub const TRANSFER_EVENT_TOPIC: H256 = H256([
+	0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+	0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+]);
+
+/// Parses a `Transfer` event log returning the `from`, `to`, and `value`.
+///
+/// The layout is detected by the number of topics: the standard one, with `from` and `to` indexed and the `value`
+/// in the data, or the one used by some older tokens, with all the arguments indexed.
+/// An ERC-721 `Transfer` has the same topics as the all indexed layout, so its `tokenId` is returned as the `value`,
+/// the logs must be filtered by the contracts known to be ERC20 tokens to tell them apart.
+/// Trailing bytes appended to the data by some contracts are ignored.
+///
+/// # Arguments
+///
+/// * `log` - The log emitted by the ERC20 contract.
+///
+pub fn parse_transfer_log(log: &Log) -> Result<(H160, H160, U256), ERC20Error> {
+	if log.topics.is_empty() || log.topics[0] != TRANSFER_EVENT_TOPIC {
+		return Err(ERC20Error::UnexpectedType);
+	}
+	let mut value_decoder: BytesToFixedNumber = match log.topics.len() {
+		3 => log.data.clone().into(),
+		4 => log.topics[3].0.to_vec().into(),
+		_ => return Err(ERC20Error::UnexpectedSize),
+	};
+	let mut from_decoder: BytesToFixedNumber = log.topics[1].0.to_vec().into();
+	let mut to_decoder: BytesToFixedNumber = log.topics[2].0.to_vec().into();
+	Ok((from_decoder.next_h160()?, to_decoder.next_h160()?, value_decoder.next_u256()?))
+}
+
+/// Topic for the WETH `Deposit(address,uint256)` event, emitted when Ether is wrapped.
+pub const DEPOSIT_EVENT_TOPIC: H256 = H256([
+	0xe1, 0xff, 0xfc, 0xc4, 0x92, 0x3d, 0x04, 0xb5, 0x59, 0xf4, 0xd2, 0x9a, 0x8b, 0xfc, 0x6c, 0xda,
+	0x04, 0xeb, 0x5b, 0x0d, 0x3c, 0x46, 0x07, 0x51, 0xc2, 0x40, 0x2c, 0x5c, 0x5c, 0xc9, 0x10, 0x9c,
+]);
+
+/// Topic for the WETH `Withdrawal(address,uint256)` event, emitted when Ether is unwrapped.
+pub const WITHDRAWAL_EVENT_TOPIC: H256 = H256([
+	0x7f, 0xcf, 0x53, 0x2c, 0x15, 0xf0, 0xa6, 0xdb, 0x0b, 0xd6, 0xd0, 0xe0, 0x38, 0xbe, 0xa7, 0x1d,
+	0x30, 0xd8, 0x08, 0xc7, 0xd9, 0x8c, 0xb3, 0xbf, 0x72, 0x68, 0xa9, 0x5b, 0xf5, 0x08, 0x1b, 0x65,
+]);
+
+/// Parses a WETH `Deposit` event log returning the `dst` and `wad`.
+///
+/// # Arguments
+///
+/// * `log` - The log emitted by the WETH contract.
+///
+pub fn parse_deposit_log(log: &Log) -> Result<(H160, U256), ERC20Error> {
+	parse_address_value_log(log, DEPOSIT_EVENT_TOPIC)
+}
+
+/// Parses a WETH `Withdrawal` event log returning the `src` and `wad`.
+///
+/// # Arguments
+///
+/// * `log` - The log emitted by the WETH contract.
+///
+pub fn parse_withdrawal_log(log: &Log) -> Result<(H160, U256), ERC20Error> {
+	parse_address_value_log(log, WITHDRAWAL_EVENT_TOPIC)
+}
+
+fn parse_address_value_log(log: &Log, topic: H256) -> Result<(H160, U256), ERC20Error> {
+	if log.topics.is_empty() || log.topics[0] != topic {
+		return Err(ERC20Error::UnexpectedType);
+	}
+	if log.topics.len() != 2 {
+		return Err(ERC20Error::UnexpectedSize);
+	}
+	let mut address_decoder: BytesToFixedNumber = log.topics[1].0.to_vec().into();
+	let mut value_decoder: BytesToFixedNumber = log.data.clone().into();
+	Ok((address_decoder.next_h160()?, value_decoder.next_u256()?))
+}
+
+/// Checks the hardcoded selectors against the Keccak-256 hash of the method signatures, returning the mismatches.
+pub fn verify_selectors() -> Result<(), Vec<ERC20Method>> {
+	let mismatches: Vec<ERC20Method> = ERC20Method::all().iter()
+		.filter(|method| match (method.signature(), method.clone().to_selector()) {
+			(Some(signature), Some(selector)) => keccak256(signature.as_bytes()).0[..4] != selector,
+			_ => true,
+		})
+		.cloned()
+		.collect();
+	if mismatches.is_empty() {
+		Ok(())
+	} else {
+		Err(mismatches)
+	}
+}
+
+/// Selector of the optional `name()` metadata method.
+pub const NAME_SELECTOR: [u8; 4] = [0x06, 0xfd, 0xde, 0x03];
+
+/// Selector of the optional `symbol()` metadata method.
+pub const SYMBOL_SELECTOR: [u8; 4] = [0x95, 0xd8, 0x9b, 0x41];
+
+/// Selector of the optional `decimals()` metadata method.
+pub const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+/// Returns the calldata for the `name()` method.
+pub fn encode_name() -> Vec<u8> {
+	NAME_SELECTOR.to_vec()
+}
+
+/// Returns the calldata for the `symbol()` method.
+pub fn encode_symbol() -> Vec<u8> {
+	SYMBOL_SELECTOR.to_vec()
+}
+
+/// Returns the calldata for the `decimals()` method.
+pub fn encode_decimals() -> Vec<u8> {
+	DECIMALS_SELECTOR.to_vec()
+}
+
+/// Decodes the output of the `name()` or `symbol()` methods.
+///
+/// Besides the dynamic `string`, it accepts the `bytes32` returned by some legacy tokens, like MKR,
+/// decoded as a null-terminated string.
+/// The `bytes32` interpretation is only used for 32 bytes outputs, any other output must be a valid dynamic `string`.
+///
+/// # Arguments
+///
+/// * `output` - The output of the call.
+///
+pub fn decode_string_return(output: &[u8]) -> Result<String, ERC20Error> {
+	if output.len() == WORD_SIZE {
+		return decode_bytes32_string(output);
+	}
+	let mut decoder: BytesToFixedNumber = output.to_vec().into();
+	String::from_utf8(decoder.next_bytes()?).map_err(|_| ERC20Error::UnexpectedType)
+}
+
+/// Decodes the 32 bytes output as a null-terminated string.
+fn decode_bytes32_string(output: &[u8]) -> Result<String, ERC20Error> {
+	let size = output.iter().position(|it| *it == 0).unwrap_or(WORD_SIZE);
+	String::from_utf8(output[..size].to_vec()).map_err(|_| ERC20Error::UnexpectedType)
+}
+
+/// Decodes the output of the `decimals()` method.
+///
+/// # Arguments
+///
+/// * `output` - The output of the call.
+///
+pub fn decode_decimals_return(output: &[u8]) -> Result<u8, ERC20Error> {
+	let mut decoder: BytesToFixedNumber = output.to_vec().into();
+	let decimals = decoder.next_u256()?;
+	if decimals > U256::from(u8::max_value()) {
+		return Err(ERC20Error::Overflow);
+	}
+	Ok(decimals.low_u32() as u8)
+}
+
+/// Serializes an `ERC20Method` as its `0x` prefixed selector hex, e.g. `"0xa9059cbb"` for `Transfer`.
+///
+/// Meant to be used with `#[serde(with = "erc20::erc20::method_as_selector")]` on the fields,
+/// `Unidentified` has no selector so it cannot be serialized.
+pub mod method_as_selector {
+	use super::ERC20Method;
+	use crate::util::{
+		decode_hex,
+		encode_hex,
+	};
+	use serde::{
+		de,
+		ser,
+		Deserialize,
+		Deserializer,
+		Serializer,
+	};
+
+	/// Serializes the method as its selector hex.
+	pub fn serialize<S: Serializer>(method: &ERC20Method, serializer: S) -> Result<S::Ok, S::Error> {
+		match method.clone().to_selector() {
+			Some(selector) => serializer.serialize_str(&encode_hex(&selector)),
+			None => Err(ser::Error::custom("the method has no selector")),
+		}
+	}
+
+	/// Deserializes the method from its selector hex.
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ERC20Method, D::Error> {
+		let selector = String::deserialize(deserializer)?;
+		let selector = decode_hex(&selector).map_err(|_| de::Error::custom("invalid selector hex"))?;
+		if selector.len() != 4 {
+			return Err(de::Error::invalid_length(selector.len(), &"4 bytes"));
+		}
+		Ok(selector.into())
+	}
+}
@@ -1,6 +1,46 @@
-use crate::erc20::ContractAddress;
-use std::str::FromStr;
-use web3::types::H160;
+use crate::{
+	erc20::{
+		all_selectors,
+		decode_decimals_return,
+		decode_string_return,
+		encode_decimals,
+		encode_name,
+		encode_symbol,
+		is_fee_on_transfer,
+		method_as_selector,
+		parse_deposit_log,
+		parse_withdrawal_log,
+		register_fee_on_transfer,
+		verify_selectors,
+		ContractAddress,
+		ERC20Method,
+		SelectorDescriptor,
+		SelectorTable,
+		DEPOSIT_EVENT_TOPIC,
+		WITHDRAWAL_EVENT_TOPIC,
+	},
+	ERC20Error,
+};
+use serde::{
+	Deserialize,
+	Serialize,
+};
+use serde_json::json;
+use std::{
+	collections::HashSet,
+	convert::{
+		TryFrom,
+		TryInto,
+	},
+	str::FromStr,
+};
+use web3::types::{
+	Bytes,
+	H160,
+	H256,
+	Log,
+	U256,
+};
 
 #[test]
 fn creating_address() {
@@ -19,3 +59,277 @@ fn usdc_address() {
 	let usdc_address: H160 = ContractAddress::USDC.into();
 	assert_eq!("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", format!("{:?}", usdc_address));
 }
+
+#[test]
+fn method_abi_fragment() {
+	assert_eq!(
+		Some(json!({
+			"type": "function",
+			"name": "transferFrom",
+			"inputs": [
+				{"name": "_from", "type": "address"},
+				{"name": "_to", "type": "address"},
+				{"name": "_value", "type": "uint256"}
+			],
+			"outputs": [{"name": "success", "type": "bool"}],
+			"stateMutability": "nonpayable"
+		})),
+		ERC20Method::TransferFrom.abi_fragment(),
+	);
+	assert_eq!(Some(json!([])), ERC20Method::TotalSupply.abi_fragment().map(|it| it["inputs"].clone()));
+	assert_eq!(None, ERC20Method::Unidentified.abi_fragment());
+}
+
+#[test]
+fn bincode_round_trip() {
+	for method in vec![
+		ERC20Method::Transfer, ERC20Method::TransferFrom, ERC20Method::Unidentified,
+		ERC20Method::UnidentifiedWith([0xd0, 0xe3, 0x0d, 0xb0]),
+	] {
+		let encoded = bincode::serialize(&method).unwrap();
+		assert_eq!(method, bincode::deserialize::<ERC20Method>(&encoded).unwrap());
+	}
+
+	for contract in vec![ContractAddress::USDC, ContractAddress::cDAI, ContractAddress::Unidentified(H160::random())] {
+		let encoded = bincode::serialize(&contract).unwrap();
+		assert_eq!(contract, bincode::deserialize::<ContractAddress>(&encoded).unwrap());
+	}
+
+	for error in vec![ERC20Error::NoTransferTransaction, ERC20Error::UnexpectedEndOfData] {
+		let encoded = bincode::serialize(&error).unwrap();
+		assert_eq!(error, bincode::deserialize::<ERC20Error>(&encoded).unwrap());
+	}
+}
+
+#[test]
+fn unidentified_method_selector() {
+	let method: ERC20Method = vec![0xd0, 0xe3, 0x0d, 0xb0, 0x01].into();
+	assert_eq!(ERC20Method::UnidentifiedWith([0xd0, 0xe3, 0x0d, 0xb0]), method);
+	let selector: Result<[u8; 4], ERC20Error> = method.try_into();
+	assert_eq!(Ok([0xd0, 0xe3, 0x0d, 0xb0]), selector);
+
+	let method: ERC20Method = vec![0xd0, 0xe3].into();
+	assert_eq!(ERC20Method::Unidentified, method);
+	let selector: Result<[u8; 4], ERC20Error> = method.try_into();
+	assert_eq!(Err(ERC20Error::UnexpectedType), selector);
+}
+
+#[test]
+fn canonicalize_unidentified_address() {
+	let usdc_address: H160 = ContractAddress::USDC.into();
+	let unidentified_usdc = ContractAddress::Unidentified(usdc_address);
+	assert_ne!(ContractAddress::USDC, unidentified_usdc);
+	assert_eq!(ContractAddress::USDC, unidentified_usdc.canonicalize());
+
+	let unknown = ContractAddress::Unidentified(H160::from_low_u64_be(1));
+	assert_eq!(unknown.clone(), unknown.canonicalize());
+	assert_eq!(ContractAddress::DAI, ContractAddress::DAI.canonicalize());
+}
+
+#[test]
+fn contract_from_symbol() {
+	assert_eq!(Ok(ContractAddress::USDC), ContractAddress::try_from("USDC"));
+	assert_eq!(Ok(ContractAddress::DAI), ContractAddress::try_from("dai"));
+	assert_eq!(Ok(ContractAddress::WBTC), ContractAddress::try_from("wBtC"));
+	assert_eq!(Ok(ContractAddress::cDAI), ContractAddress::try_from("cDAI"));
+	assert_eq!(Err(ERC20Error::UnexpectedType), ContractAddress::try_from("DOGE"));
+	assert_eq!(Some("USDC"), ContractAddress::USDC.symbol());
+}
+
+#[test]
+fn known_contract_addresses_parse() {
+	let contract_and_address = ContractAddress::contract_and_address();
+	assert_eq!(16, contract_and_address.len());
+
+	let addresses: HashSet<H160> = contract_and_address.values().cloned().collect();
+	assert_eq!(contract_and_address.len(), addresses.len());
+
+	for (contract, address) in contract_and_address {
+		assert!(contract.symbol().is_some());
+		let from_address: ContractAddress = address.into();
+		assert_eq!(contract, from_address);
+		let from_contract: H160 = contract.into();
+		assert_eq!(address, from_contract);
+	}
+}
+
+#[test]
+fn weth_deposit_and_withdrawal_logs() {
+	let address = H160::random();
+	let mut address_topic = [0u8; 32];
+	address_topic[12..].copy_from_slice(&address.0);
+	let mut data = [0u8; 32];
+	U256::from(1000).to_big_endian(&mut data);
+
+	let mut log = Log {
+		address: H160::from_str("c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2").unwrap(),
+		topics: vec![DEPOSIT_EVENT_TOPIC, address_topic.into()],
+		data: Bytes(data.to_vec()),
+		block_hash: None,
+		block_number: None,
+		transaction_hash: None,
+		transaction_index: None,
+		log_index: None,
+		transaction_log_index: None,
+		log_type: None,
+		removed: None,
+	};
+	assert_eq!(Ok((address, U256::from(1000))), parse_deposit_log(&log));
+	assert_eq!(Err(ERC20Error::UnexpectedType), parse_withdrawal_log(&log));
+
+	log.topics[0] = WITHDRAWAL_EVENT_TOPIC;
+	assert_eq!(Ok((address, U256::from(1000))), parse_withdrawal_log(&log));
+
+	log.topics.push(H256::random());
+	assert_eq!(Err(ERC20Error::UnexpectedSize), parse_withdrawal_log(&log));
+}
+
+#[test]
+fn selector_table_collisions() {
+	let mut table = SelectorTable::with_erc20();
+	assert_eq!(Ok(()), table.check_collisions());
+	assert_eq!(Some("balanceOf"), table.get([0x70, 0xa0, 0x82, 0x31]).map(|it| it.name.as_str()));
+	assert_eq!(Some("send"), table.get([0x9b, 0xd9, 0xbb, 0xc6]).map(|it| it.name.as_str()));
+
+	table.register([0xd0, 0xe3, 0x0d, 0xb0], SelectorDescriptor { name: "deposit".to_string() });
+	assert_eq!(Ok(()), table.check_collisions());
+	table.register([0xd0, 0xe3, 0x0d, 0xb0], SelectorDescriptor { name: "deposit".to_string() });
+	assert_eq!(Err(vec![[0xd0, 0xe3, 0x0d, 0xb0]]), table.check_collisions());
+
+	table.register([0x70, 0xa0, 0x82, 0x31], SelectorDescriptor { name: "passphrase_calculate_transfer".to_string() });
+	table.register([0x70, 0xa0, 0x82, 0x31], SelectorDescriptor { name: "branch_passphrase_public".to_string() });
+	assert_eq!(Err(vec![[0xd0, 0xe3, 0x0d, 0xb0], [0x70, 0xa0, 0x82, 0x31]]), table.check_collisions());
+	assert_eq!(Some("balanceOf"), table.get([0x70, 0xa0, 0x82, 0x31]).map(|it| it.name.as_str()));
+}
+
+#[test]
+fn fee_on_transfer_registry() {
+	assert!(is_fee_on_transfer(H160::from_str("45804880De22913dAFE09f4980848ECE6EcbAf78").unwrap()));
+
+	let token = H160::random();
+	assert!(!is_fee_on_transfer(token));
+	register_fee_on_transfer(token);
+	assert!(is_fee_on_transfer(token));
+}
+
+#[test]
+fn metadata_calls() {
+	assert_eq!(hex::decode("06fdde03").unwrap(), encode_name());
+	assert_eq!(hex::decode("95d89b41").unwrap(), encode_symbol());
+	assert_eq!(hex::decode("313ce567").unwrap(), encode_decimals());
+
+	let output = hex::decode(
+		"0000000000000000000000000000000000000000000000000000000000000020\
+		0000000000000000000000000000000000000000000000000000000000000004\
+		5553444300000000000000000000000000000000000000000000000000000000"
+	).unwrap();
+	assert_eq!(Ok("USDC".to_string()), decode_string_return(&output));
+
+	// The offset and the length with the data missing.
+	let output = hex::decode(
+		"0000000000000000000000000000000000000000000000000000000000000020\
+		0000000000000000000000000000000000000000000000000000000000000004"
+	).unwrap();
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), decode_string_return(&output));
+
+	let output = hex::decode("0000000000000000000000000000000000000000000000000000000000000006").unwrap();
+	assert_eq!(Ok(6), decode_decimals_return(&output));
+	let output = hex::decode("0000000000000000000000000000000000000000000000000000000000000100").unwrap();
+	assert_eq!(Err(ERC20Error::Overflow), decode_decimals_return(&output));
+}
+
+#[test]
+fn decode_legacy_bytes32_string() {
+	// MKR `symbol()` returns a bytes32.
+	let output = hex::decode("4d4b520000000000000000000000000000000000000000000000000000000000").unwrap();
+	assert_eq!(Ok("MKR".to_string()), decode_string_return(&output));
+
+	// DAI `name()` returns a dynamic string.
+	let output = hex::decode(
+		"0000000000000000000000000000000000000000000000000000000000000020\
+		000000000000000000000000000000000000000000000000000000000000000e\
+		44616920537461626c65636f696e000000000000000000000000000000000000"
+	).unwrap();
+	assert_eq!(Ok("Dai Stablecoin".to_string()), decode_string_return(&output));
+
+	let output = hex::decode("ff00000000000000000000000000000000000000000000000000000000000000").unwrap();
+	assert_eq!(Err(ERC20Error::UnexpectedType), decode_string_return(&output));
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), decode_string_return(&[]));
+
+	// A truncated dynamic string is not taken as a bytes32.
+	let output = hex::decode(
+		"0000000000000000000000000000000000000000000000000000000000000020\
+		000000000000000000000000000000000000000000000000000000000000000e\
+		44616920537461626c65"
+	).unwrap();
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), decode_string_return(&output));
+}
+
+#[test]
+fn all_known_selectors() {
+	let selectors = all_selectors();
+	assert_eq!(ERC20Method::all().len(), selectors.len());
+	assert!(selectors.contains(&[0xa9, 0x05, 0x9c, 0xbb]));
+	assert!(selectors.contains(&[0x9b, 0xd9, 0xbb, 0xc6]));
+	for selector in selectors {
+		assert_ne!(ERC20Method::UnidentifiedWith(*selector), ERC20Method::from(selector.to_vec()));
+	}
+}
+
+#[test]
+fn const_selector() {
+	const TRANSFER_FROM: Option<[u8; 4]> = ERC20Method::TransferFrom.to_selector();
+	assert_eq!(Some([0x23, 0xb8, 0x72, 0xdd]), TRANSFER_FROM);
+	assert_eq!(None, ERC20Method::Unidentified.to_selector());
+	assert_eq!(Some([1, 2, 3, 4]), ERC20Method::UnidentifiedWith([1, 2, 3, 4]).to_selector());
+}
+
+#[test]
+fn contract_address_order() {
+	let mut contracts = vec![
+		ContractAddress::Unidentified(H160::from_low_u64_be(2)),
+		ContractAddress::USDC,
+		ContractAddress::cDAI,
+		ContractAddress::Unidentified(H160::from_low_u64_be(1)),
+		ContractAddress::DAI,
+		ContractAddress::BAT,
+	];
+	contracts.sort();
+
+	assert_eq!(vec![
+		ContractAddress::BAT,
+		ContractAddress::cDAI,
+		ContractAddress::DAI,
+		ContractAddress::USDC,
+		ContractAddress::Unidentified(H160::from_low_u64_be(1)),
+		ContractAddress::Unidentified(H160::from_low_u64_be(2)),
+	], contracts);
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+struct CompactCall {
+	#[serde(with = "method_as_selector")]
+	method: ERC20Method,
+}
+
+#[test]
+fn method_selector_serde() {
+	let call = CompactCall { method: ERC20Method::Transfer };
+	let serialized = serde_json::to_value(&call).unwrap();
+	assert_eq!(json!({"method": "0xa9059cbb"}), serialized);
+	assert_eq!(call, serde_json::from_value(serialized).unwrap());
+
+	let call = CompactCall { method: ERC20Method::UnidentifiedWith([0xde, 0xad, 0xbe, 0xef]) };
+	assert_eq!(call, serde_json::from_value(serde_json::to_value(&call).unwrap()).unwrap());
+
+	assert!(serde_json::to_value(&CompactCall { method: ERC20Method::Unidentified }).is_err());
+	assert!(serde_json::from_value::<CompactCall>(json!({"method": "0xa9059c"})).is_err());
+	assert_eq!(json!("transfer"), serde_json::to_value(&ERC20Method::Transfer).unwrap());
+}
+
+#[test]
+fn selectors_match_signatures() {
+	assert_eq!(Ok(()), verify_selectors());
+	assert_eq!(Some("transfer(address,uint256)"), ERC20Method::Transfer.signature());
+	assert_eq!(None, ERC20Method::UnidentifiedWith([0xa9, 0x05, 0x9c, 0xbb]).signature());
+}
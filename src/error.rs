@@ -4,6 +4,7 @@ use serde::{
 	Deserialize,
 	Serialize,
 };
+use std::fmt;
 
 /// Possible transaction errors.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -17,4 +18,20 @@ pub enum ERC20Error {
 	UnexpectedEndOfData,
 	/// Returned when the type or value used is not expected for the operation.
 	UnexpectedType,
+	/// Returned when an arithmetic operation overflows.
+	Overflow,
+	/// Returned when a subtraction would be negative.
+	Underflow,
+	/// The transaction was not found by the node.
+	TransactionNotFound,
+	/// The request to the node failed, with the error message.
+	RequestFailed(String),
+	/// Writing the output failed, with the error message.
+	WriteFailed(String),
+}
+
+impl fmt::Display for ERC20Error {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{:?}", self)
+	}
 }
@@ -12,6 +12,8 @@ pub mod util;
 mod util_tests;
 /// Ethereum transfer abstraction.
 pub mod transfer;
+#[cfg(test)]
+mod transfer_tests;
 /// ERC20 specific information.
 pub mod erc20;
 #[cfg(test)]
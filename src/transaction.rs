@@ -1,31 +1,60 @@
 ///! web3 transaction specific operations.
 
 use crate::{
-	erc20::ERC20Method,
+	erc20::{
+		is_fee_on_transfer,
+		ContractAddress,
+		ERC20Method,
+	},
 	error::ERC20Error,
 	transfer::{
+		LogTransfer,
+		TransferKey,
+		TransferSummary,
 		TransferType,
 		Transfer,
 	},
-	util::BytesToFixedNumber,
+	util::{
+		decode_hex,
+		encode_hex,
+		BytesToFixedNumber,
+		FixedNumberToBytes,
+		WORD_SIZE,
+	},
 };
 use serde::{
 	Deserialize,
 	Serialize,
 };
-use std::convert::TryFrom;
+use std::{
+	collections::{
+		BTreeMap,
+		HashMap,
+		HashSet,
+	},
+	convert::{
+		TryFrom,
+		TryInto,
+	},
+	fmt,
+	io::Write,
+};
 use web3::types::{
+	Bytes,
 	H160,
 	H256,
 	Index,
+	Log,
 	Transaction,
 	U64,
 	U256,
 };
 
 /// Identifies an Ethereum transaction as a transfer, contract invocation, creation, or other.
+///
+/// It is serialized with the variant in the `kind` field alongside the transaction fields.
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(tag = "kind", rename_all = "camelCase")]
 pub enum ParsedTransaction {
 	/// Ether transfer transaction.
 	EthereumTransfer(Transaction),
@@ -38,8 +67,31 @@ pub enum ParsedTransaction {
 }
 
 impl From<Transaction> for ParsedTransaction {
+	/// Parses the transaction, not considering zero value transactions as Ether transfers.
 	#[inline]
 	fn from(transaction: Transaction) -> Self {
+		Self::parse(transaction, false)
+	}
+}
+
+impl Default for ParsedTransaction {
+	fn default() -> Self {
+		From::<Transaction>::from(Default::default())
+	}
+}
+
+impl ParsedTransaction {
+	/// Parses the transaction.
+	///
+	/// A transaction with a recipient and no input is an Ether transfer when it sends some value.
+	/// The ones with zero value do not move any asset, so they are `Other` unless `treat_zero_value_as_transfer` is set.
+	///
+	/// # Arguments
+	///
+	/// * `transaction` - The transaction to be parsed.
+	/// * `treat_zero_value_as_transfer` - If zero value transactions with no input are Ether transfers.
+	///
+	pub fn parse(transaction: Transaction, treat_zero_value_as_transfer: bool) -> Self {
 		match transaction.to {
 			None => if transaction.input.0.is_empty() {
 				Self::Other(transaction)
@@ -48,16 +100,33 @@ impl From<Transaction> for ParsedTransaction {
 			},
 			Some(_) => if !transaction.input.0.is_empty() {
 				Self::ContractInvocation(transaction.into())
+			} else if treat_zero_value_as_transfer || !transaction.value.is_zero() {
+				Self::EthereumTransfer(transaction)
 			} else {
 				Self::Other(transaction)
 			},
 		}
 	}
-}
 
-impl Default for ParsedTransaction {
-	fn default() -> Self {
-		From::<Transaction>::from(Default::default())
+	/// Checks if a contract creation has a bare ERC20 `transfer` call as the init code, likely missing the recipient.
+	///
+	/// It flags data quality issues, like mempool feeds dropping the `to` field.
+	pub fn looks_like_misrouted_transfer(&self) -> bool {
+		match self {
+			Self::ContractCreation(transaction) => {
+				let input = &transaction.input.0;
+				input.len() == 4 + 2 * WORD_SIZE && ERC20Method::from(input.clone()) == ERC20Method::Transfer
+			}
+			_ => false,
+		}
+	}
+
+	/// Returns the value sent along with a contract creation, `None` for other transactions.
+	pub fn creation_value(&self) -> Option<U256> {
+		match self {
+			Self::ContractCreation(transaction) => Some(transaction.value),
+			_ => None,
+		}
 	}
 }
 
@@ -74,12 +143,65 @@ impl From<Transaction> for TransactionContractInvocation {
 	fn from(transaction: Transaction) -> Self {
 		let method = transaction.clone().input.0.into();
 		match method {
-			ERC20Method::Unidentified => Self::Other(transaction),
+			ERC20Method::Unidentified | ERC20Method::UnidentifiedWith(_) => Self::Other(transaction),
 			_ => Self::ERC20(method, transaction),
 		}
 	}
 }
 
+impl From<&TransactionContractInvocation> for Option<ERC20Method> {
+	fn from(invocation: &TransactionContractInvocation) -> Self {
+		match invocation {
+			TransactionContractInvocation::ERC20(method, _) => Some(method.clone()),
+			TransactionContractInvocation::Other(_) => None,
+		}
+	}
+}
+
+impl TransactionContractInvocation {
+	/// Returns the transaction for the contract invocation.
+	pub fn transaction(&self) -> &Transaction {
+		match self {
+			Self::ERC20(_, transaction) => transaction,
+			Self::Other(transaction) => transaction,
+		}
+	}
+
+	/// Decodes an `approve` invocation returning the `spender` and `value`.
+	pub fn approval(&self) -> Result<(H160, U256), ERC20Error> {
+		match self {
+			Self::ERC20(ERC20Method::Approve, transaction) => {
+				let mut resp: BytesToFixedNumber = transaction.input.clone().into();
+				resp.skip(4)?;
+				Ok((resp.next_h160()?, resp.next_u256()?))
+			}
+			_ => Err(ERC20Error::UnexpectedType),
+		}
+	}
+
+	/// Checks if it is an `approve` with zero value, revoking the allowance.
+	pub fn is_revoke(&self) -> bool {
+		self.approval().map_or(false, |(_, value)| value.is_zero())
+	}
+
+	/// Checks if it is an `approve` with the maximum value, allowing unlimited spending.
+	pub fn is_infinite_approval(&self) -> bool {
+		self.approval().map_or(false, |(_, value)| value == U256::max_value())
+	}
+
+	/// Returns the 4 bytes method selector, or `None` if the input is shorter than that.
+	pub fn selector(&self) -> Option<[u8; 4]> {
+		let input = &self.transaction().input.0;
+		if input.len() < 4 {
+			None
+		} else {
+			let mut resp = [0; 4];
+			resp.clone_from_slice(&input[..4]);
+			Some(resp)
+		}
+	}
+}
+
 /// Transaction and transaction type information for asset transfers.
 ///
 /// ```
@@ -116,6 +238,9 @@ impl From<Transaction> for TransactionContractInvocation {
 ///
 /// let resp: Result<TransactionAndTransferType, ERC20Error> = transaction.clone().try_into();
 /// ```
+///
+/// The `Transfer` getters panic if the transaction cannot be decoded, what can only happen for deserialized values,
+/// use `checked` on them so the getters never panic.
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TransactionAndTransferType {
@@ -123,12 +248,45 @@ pub struct TransactionAndTransferType {
 	transfer_type: TransferType,
 }
 
+/// Flat representation of a transfer with primitive types.
+///
+/// Hashes and addresses are `0x` prefixed lowercase hex strings, and the value is a decimal string.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferRow {
+	/// Transaction hash.
+	pub tx_hash: String,
+	/// Sender of the transfer.
+	pub from: String,
+	/// Recipient of the transfer.
+	pub to: String,
+	/// Value of the transfer in base units.
+	pub value: String,
+	/// ERC20 contract address, `None` for Ether transfers.
+	pub contract: Option<String>,
+	/// Block number, `None` for pending transactions.
+	pub block_number: Option<u64>,
+}
+
 impl TryFrom<Transaction> for TransactionAndTransferType {
 	type Error = ERC20Error;
 
+	/// Converts the transaction, not considering zero value transactions as Ether transfers.
 	fn try_from(value: Transaction) -> Result<Self, Self::Error> {
-		let parsed_transaction: ParsedTransaction = value.into();
-		match parsed_transaction {
+		Self::try_from_with(value, false)
+	}
+}
+
+impl TransactionAndTransferType {
+	/// Converts the transaction into a transfer, see `ParsedTransaction::parse`.
+	///
+	/// # Arguments
+	///
+	/// * `value` - The transaction to be converted.
+	/// * `treat_zero_value_as_transfer` - If zero value transactions with no input are Ether transfers.
+	///
+	pub fn try_from_with(value: Transaction, treat_zero_value_as_transfer: bool) -> Result<Self, ERC20Error> {
+		match ParsedTransaction::parse(value, treat_zero_value_as_transfer) {
 			ParsedTransaction::EthereumTransfer(transaction) => Ok(Self {
 				transaction,
 				transfer_type: TransferType::Ethereum,
@@ -138,14 +296,15 @@ impl TryFrom<Transaction> for TransactionAndTransferType {
 				match contract_invocation {
 					TransactionContractInvocation::ERC20(method, transaction) => {
 						match method {
-							ERC20Method::Transfer => Ok(Self {
-								transaction,
-								transfer_type: TransferType::ERC20,
-							}),
-							ERC20Method::TransferFrom => Ok(Self {
-								transaction,
-								transfer_type: TransferType::ERC20,
-							}),
+							ERC20Method::Transfer | ERC20Method::TransferFrom | ERC20Method::Send => {
+								let resp = Self {
+									transaction,
+									transfer_type: TransferType::ERC20,
+								};
+								// Making sure the input can be decoded so the `Transfer` getters do not panic.
+								resp.get_from_to_value()?;
+								Ok(resp)
+							}
 							_ => Err(ERC20Error::NoTransferTransaction),
 						}
 					}
@@ -156,9 +315,56 @@ impl TryFrom<Transaction> for TransactionAndTransferType {
 			ParsedTransaction::Other(_) => Err(ERC20Error::NoTransferTransaction),
 		}
 	}
-}
 
-impl TransactionAndTransferType {
+	/// Creates an Ether transfer with a minimal transaction, useful for test fixtures.
+	///
+	/// # Arguments
+	///
+	/// * `from` - Sender of the transfer.
+	/// * `to` - Recipient of the transfer.
+	/// * `value` - Value of the transfer.
+	/// * `tx_hash` - Transaction hash for the transfer.
+	///
+	pub fn ethereum(from: H160, to: H160, value: U256, tx_hash: H256) -> Self {
+		Self {
+			transaction: Transaction {
+				hash: tx_hash,
+				from,
+				to: Some(to),
+				value,
+				..Default::default()
+			},
+			transfer_type: TransferType::Ethereum,
+		}
+	}
+
+	/// Creates an ERC20 `transfer` with a minimal transaction, requiring the `test-util` feature.
+	///
+	/// # Arguments
+	///
+	/// * `contract` - Address of the token contract.
+	/// * `from` - Sender of the transfer.
+	/// * `to` - Recipient of the transfer.
+	/// * `value` - Value of the transfer.
+	///
+	#[cfg(feature = "test-util")]
+	pub fn mock_erc20_transfer(contract: H160, from: H160, to: H160, value: U256) -> Self {
+		let input = FixedNumberToBytes::default()
+			.push_selector(ERC20Method::Transfer).unwrap()
+			.push_h160(&to)
+			.push_u256(&value)
+			.to_vec();
+		Self {
+			transaction: Transaction {
+				from,
+				to: Some(contract),
+				input: Bytes(input),
+				..Default::default()
+			},
+			transfer_type: TransferType::ERC20,
+		}
+	}
+
 	/// Gets information from the transaction.
 	/// The `from`, `to`, and `value` regardless if it is an ERC20 or Ether transfer.
 	pub fn get_from_to_value(&self) -> Result<(H160, H160, U256), ERC20Error> {
@@ -197,6 +403,12 @@ impl TransactionAndTransferType {
 								to_v = resp.next_h160()?;
 								value_v = resp.next_u256()?;
 							}
+							ERC20Method::Send => {
+								let (the_to, the_value, _) = decode_erc777_send(&transaction.input.0)?;
+								from_v = transaction.from;
+								to_v = the_to;
+								value_v = the_value;
+							}
 							_ => {
 								return Err(ERC20Error::NoTransferTransaction);
 							}
@@ -210,46 +422,188 @@ impl TransactionAndTransferType {
 		}
 		Ok((from_v, to_v, value_v))
 	}
-}
 
-impl Transfer for TransactionAndTransferType {
-	fn from(&self) -> H160 {
-		match self.get_from_to_value() {
-			Ok((the_from, _, _)) => the_from,
-			Err(err) => {
-				panic!("Unexpected transaction for TransactionAndTransferType::from {:?}", err)
+	/// Gets the `from`, `to`, `value`, and the contract address, `None` for Ether transfers.
+	pub fn get_transfer_details(&self) -> Result<(H160, H160, U256, Option<H160>), ERC20Error> {
+		let (from_v, to_v, value_v) = self.get_from_to_value()?;
+		let contract = match self.transfer_type {
+			TransferType::Ethereum => None,
+			TransferType::ERC20 => Some(self.transaction.to.ok_or(ERC20Error::NoTransferTransaction)?),
+		};
+		Ok((from_v, to_v, value_v, contract))
+	}
+
+	/// Checks that the transfer can be decoded, so its `Transfer` getters cannot panic.
+	///
+	/// Only needed for deserialized values, the `TryFrom<Transaction>` conversion already checks it.
+	pub fn checked(self) -> Result<Self, ERC20Error> {
+		self.get_transfer_details()?;
+		Ok(self)
+	}
+
+	/// Converts the transfer into its compact summary, decoding the input once.
+	///
+	/// Returns the decoding error instead of panicking like the `Transfer` getters.
+	pub fn into_summary(self) -> Result<TransferSummary, ERC20Error> {
+		let (from, to, value, contract) = self.get_transfer_details()?;
+		Ok(TransferSummary {
+			tx_hash: self.transaction.hash,
+			from,
+			to,
+			value,
+			contract,
+		})
+	}
+
+	/// Converts the transfer into a flat row of primitive types, ready to be stored in a database.
+	pub fn to_row(&self) -> Result<TransferRow, ERC20Error> {
+		let (from_v, to_v, value_v, contract) = self.get_transfer_details()?;
+		Ok(TransferRow {
+			tx_hash: encode_hex(&self.transaction.hash.0),
+			from: encode_hex(&from_v.0),
+			to: encode_hex(&to_v.0),
+			value: value_v.to_string(),
+			contract: contract.map(|it| encode_hex(&it.0)),
+			block_number: self.transaction.block_number.map(|it| it.as_u64()),
+		})
+	}
+
+	/// Checks if it is an ERC20 transfer of a token in `ContractAddress`, false for Ether transfers.
+	pub fn is_known_token(&self) -> bool {
+		match (&self.transfer_type, self.transaction.to) {
+			(TransferType::ERC20, Some(address)) => {
+				!matches!(ContractAddress::from(address), ContractAddress::Unidentified(_))
 			}
+			_ => false,
 		}
 	}
 
-	fn to(&self) -> H160 {
-		match self.get_from_to_value() {
-			Ok((_, the_to, _)) => the_to,
-			Err(err) => {
-				panic!("Unexpected transaction for TransactionAndTransferType::to {:?}", err)
-			}
+	/// Checks if the calldata value is what the recipient gets.
+	///
+	/// Returns false for the fee-on-transfer tokens, for them the `Transfer` event should be trusted instead.
+	/// See `erc20::register_fee_on_transfer`.
+	pub fn value_is_authoritative(&self) -> bool {
+		match (&self.transfer_type, self.transaction.to) {
+			(TransferType::ERC20, Some(address)) => !is_fee_on_transfer(address),
+			_ => true,
 		}
 	}
 
-	fn contract(&self) -> Option<H160> {
-		match self.transfer_type {
-			TransferType::Ethereum => None,
-			TransferType::ERC20 => {
-				match self.transaction.to {
-					None => panic!("Unexpected transaction for TransactionAndTransferType::contract"),
-					Some(to) => Some(to),
-				}
-			}
+	/// Checks if an ERC20 transfer sends the tokens to the token contract itself, usually losing them.
+	///
+	/// Returns false for Ether transfers and when the recipient cannot be decoded.
+	pub fn is_sent_to_token_contract(&self) -> bool {
+		match (&self.transfer_type, self.transaction.to, self.try_to()) {
+			(TransferType::ERC20, Some(contract), Ok(the_to)) => contract == the_to,
+			_ => false,
+		}
+	}
+
+	/// Checks if it is an ERC-777 `send` rather than an ERC20 transfer.
+	pub fn is_erc777_send(&self) -> bool {
+		self.transfer_type == TransferType::ERC20
+			&& ERC20Method::from(self.transaction.input.0.clone()) == ERC20Method::Send
+	}
+
+	/// Returns the transaction input.
+	pub fn calldata(&self) -> Vec<u8> {
+		self.transaction.input.0.clone()
+	}
+
+	/// Encodes the calldata again from the decoded sender, recipient, and value, empty for Ether transfers.
+	///
+	/// It matches `calldata` for standard encoded transfers, so it can be used to verify the encoding.
+	pub fn rebuild_calldata(&self) -> Result<Vec<u8>, ERC20Error> {
+		let (from_v, to_v, value_v) = self.get_from_to_value()?;
+		if self.transfer_type == TransferType::Ethereum {
+			return Ok(Vec::new());
 		}
+		let method: ERC20Method = self.transaction.input.0.clone().into();
+		let selector: [u8; 4] = method.clone().try_into()?;
+		let mut encoder: FixedNumberToBytes = Default::default();
+		encoder.push_vec(&selector);
+		if method == ERC20Method::TransferFrom {
+			encoder.push_h160(&from_v);
+		}
+		encoder.push_h160(&to_v);
+		encoder.push_u256(&value_v);
+		if method == ERC20Method::Send {
+			let (_, _, data) = decode_erc777_send(&self.transaction.input.0)?;
+			// The data is the only dynamic argument, right after the three head words.
+			encoder.push_u256(&U256::from(3 * WORD_SIZE));
+			encoder.push_u256(&U256::from(data.len()));
+			encoder.push_vec(&data);
+			encoder.push_vec(&vec![0; (WORD_SIZE - data.len() % WORD_SIZE) % WORD_SIZE]);
+		}
+		Ok(encoder.into())
 	}
 
-	fn value(&self) -> U256 {
+	/// Returns the spender initiating a delegated `transferFrom`, the transaction sender, `None` for other transfers.
+	///
+	/// The tokens are sent from the decoded `from` argument, returned by the `Transfer` getters.
+	pub fn spender(&self) -> Option<H160> {
+		match (&self.transfer_type, ERC20Method::from(self.transaction.input.0.clone())) {
+			(TransferType::ERC20, ERC20Method::TransferFrom) => Some(self.transaction.from),
+			_ => None,
+		}
+	}
+
+	/// Returns the recipient decoding only its argument, so it is available even if the value is truncated.
+	///
+	/// Returns `None` if the recipient itself cannot be decoded.
+	pub fn best_effort_to(&self) -> Option<H160> {
+		if self.transfer_type == TransferType::Ethereum {
+			return self.transaction.to;
+		}
+		let arguments_before = match ERC20Method::from(self.transaction.input.0.clone()) {
+			ERC20Method::Transfer | ERC20Method::Send => 0,
+			ERC20Method::TransferFrom => 1,
+			_ => return None,
+		};
+		let mut decoder: BytesToFixedNumber = self.transaction.input.clone().into();
+		decoder.skip(4 + arguments_before * WORD_SIZE).ok()?;
+		decoder.next_h160().ok()
+	}
+
+	/// Boxes the transfer as a `Transfer` trait object.
+	///
+	/// Useful to store transfers from different sources in the same collection.
+	pub fn into_boxed(self) -> Box<dyn Transfer> {
+		Box::new(self)
+	}
+}
+
+impl fmt::Display for TransactionAndTransferType {
+	/// One line summary of the transfer, like `ERC20 transfer 0x0000…0001 -> 0x6748…ff2b value 1000 (USDC) tx 0x43a5…bcee`.
+	///
+	/// Addresses and the hash are truncated, the contract is shown by its symbol when known.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let kind = match self.transfer_type {
+			TransferType::Ethereum => "Ether",
+			TransferType::ERC20 => "ERC20",
+		};
 		match self.get_from_to_value() {
-			Ok((_, _, the_value)) => the_value,
-			Err(err) => {
-				panic!("Unexpected transaction for TransactionAndTransferType::value {:?}", err)
+			Ok((from_v, to_v, value_v)) => write!(f, "{} transfer {} -> {} value {}", kind, from_v, to_v, value_v)?,
+			Err(err) => write!(f, "{} transfer {:?}", kind, err)?,
+		}
+		if let (TransferType::ERC20, Some(address)) = (&self.transfer_type, self.transaction.to) {
+			let contract: ContractAddress = address.into();
+			match contract.symbol() {
+				Some(symbol) => write!(f, " ({})", symbol)?,
+				None => write!(f, " ({})", address)?,
 			}
 		}
+		write!(f, " tx {}", self.transaction.hash)
+	}
+}
+
+impl Transfer for TransactionAndTransferType {
+	/// Returns the contract invoked, `None` for Ether transfers.
+	fn contract(&self) -> Option<H160> {
+		match self.transfer_type {
+			TransferType::Ethereum => None,
+			TransferType::ERC20 => self.transaction.to,
+		}
 	}
 
 	fn tx_hash(&self) -> H256 {
@@ -267,4 +621,867 @@ impl Transfer for TransactionAndTransferType {
 	fn transaction_index(&self) -> Option<Index> {
 		self.transaction.transaction_index
 	}
+
+	fn try_from_addr(&self) -> Result<H160, ERC20Error> {
+		self.get_from_to_value().map(|(the_from, _, _)| the_from)
+	}
+
+	fn try_to(&self) -> Result<H160, ERC20Error> {
+		self.get_from_to_value().map(|(_, the_to, _)| the_to)
+	}
+
+	fn try_value(&self) -> Result<U256, ERC20Error> {
+		self.get_from_to_value().map(|(_, _, the_value)| the_value)
+	}
+}
+
+/// Minimal call frame from a transaction trace.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CallFrame {
+	/// Caller of the frame.
+	pub from: H160,
+	/// Address called in the frame.
+	pub to: H160,
+	/// Input data for the call.
+	pub input: Vec<u8>,
+	/// Ether value sent in the call.
+	pub value: U256,
+	/// Nested calls performed by the frame.
+	pub calls: Vec<CallFrame>,
+}
+
+/// Parses the ERC20 transfers performed in the call frames of a trace, including the nested ones.
+///
+/// The transfers are returned in the order the calls appear in the trace.
+///
+/// # Arguments
+///
+/// * `tx_hash` - Hash of the traced transaction.
+/// * `traces` - Top level call frames.
+///
+pub fn parse_trace_calls(tx_hash: H256, traces: &[CallFrame]) -> Vec<TransactionAndTransferType> {
+	let mut resp = Vec::new();
+	for frame in traces {
+		let transaction = Transaction {
+			hash: tx_hash,
+			from: frame.from,
+			to: Some(frame.to),
+			value: frame.value,
+			input: Bytes(frame.input.clone()),
+			..Default::default()
+		};
+		let transfer: Result<TransactionAndTransferType, ERC20Error> = transaction.try_into();
+		if let Ok(transfer) = transfer {
+			resp.push(transfer);
+		}
+		resp.append(&mut parse_trace_calls(tx_hash, &frame.calls));
+	}
+	resp
+}
+
+/// Selector for the `multicall(bytes[])` method.
+pub const MULTICALL_SELECTOR: [u8; 4] = [0xac, 0x96, 0x50, 0xd8];
+
+/// Decodes the calls batched by a `multicall(bytes[])` invocation, classifying each one as an ERC20 method.
+///
+/// # Arguments
+///
+/// * `input` - The transaction input, including the method selector.
+///
+pub fn decode_multicall(input: &[u8]) -> Result<Vec<ERC20Method>, ERC20Error> {
+	if !input.starts_with(&MULTICALL_SELECTOR) {
+		return Err(ERC20Error::UnexpectedType);
+	}
+	let mut decoder: BytesToFixedNumber = input[4..].to_vec().into();
+	let calls = decoder.next_bytes_array()?;
+	Ok(calls.into_iter().map(|call| call.into()).collect())
+}
+
+/// Selector of the EIP-2612 `permit(address,address,uint256,uint256,uint8,bytes32,bytes32)` method.
+pub const PERMIT_SELECTOR: [u8; 4] = [0xd5, 0x05, 0xac, 0xcf];
+
+/// Checks if a `multicall(bytes[])` batches a `permit` followed by a `transferFrom`, a gasless approval and transfer.
+///
+/// Returns false if the input is not a multicall or cannot be decoded.
+///
+/// # Arguments
+///
+/// * `input` - The transaction input, including the method selector.
+///
+pub fn is_permit_transfer(input: &[u8]) -> bool {
+	match decode_multicall(input) {
+		Ok(methods) => match methods.iter().position(|it| *it == ERC20Method::UnidentifiedWith(PERMIT_SELECTOR)) {
+			Some(permit) => methods[permit + 1..].contains(&ERC20Method::TransferFrom),
+			None => false,
+		},
+		Err(_) => false,
+	}
+}
+
+/// How `filter_by_addresses` treats the transfers touching the addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FilterMode {
+	/// Keeps only the transfers touching any of the addresses.
+	Include,
+	/// Drops the transfers touching any of the addresses.
+	Exclude,
+}
+
+/// Filters the transfers having any of the addresses as sender or recipient.
+///
+/// Transfers that cannot be decoded are considered as not touching the addresses.
+///
+/// # Arguments
+///
+/// * `transfers` - Transfers to be filtered.
+/// * `addresses` - Addresses to look for.
+/// * `mode` - If the transfers touching the addresses are kept or dropped.
+///
+pub fn filter_by_addresses<'a, I>(
+	transfers: I, addresses: &'a HashSet<H160>, mode: FilterMode,
+) -> impl Iterator<Item = TransactionAndTransferType> + 'a
+	where I: Iterator<Item = TransactionAndTransferType> + 'a {
+	transfers.filter(move |transfer| {
+		let touches = transfer.try_from_addr().map_or(false, |it| addresses.contains(&it)) ||
+			transfer.try_to().map_or(false, |it| addresses.contains(&it));
+		match mode {
+			FilterMode::Include => touches,
+			FilterMode::Exclude => !touches,
+		}
+	})
+}
+
+/// Returns the number of 32 bytes words after the method selector, `None` if there is no selector.
+///
+/// # Arguments
+///
+/// * `tx` - The transaction to be checked.
+///
+pub fn input_word_count(tx: &Transaction) -> Option<usize> {
+	if tx.input.0.len() < 4 {
+		None
+	} else {
+		Some((tx.input.0.len() - 4) / 32)
+	}
+}
+
+/// Checks if the input after the method selector is not a multiple of 32 bytes.
+///
+/// It often indicates packed or non-standard encoding.
+///
+/// # Arguments
+///
+/// * `tx` - The transaction to be checked.
+///
+pub fn has_ragged_input(tx: &Transaction) -> bool {
+	tx.input.0.len() >= 4 && (tx.input.0.len() - 4) % 32 != 0
+}
+
+/// Shape of a transaction input, a cheap categorical feature with no decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum InputClass {
+	/// No input, like Ether transfers.
+	Empty,
+	/// Only the method selector, like `totalSupply`.
+	SelectorOnly,
+	/// The selector and one word, like `balanceOf`.
+	OneWord,
+	/// The selector and two words, like `transfer`.
+	TwoWords,
+	/// The selector and three words, like `transferFrom`.
+	ThreeWords,
+	/// Any other size, like dynamic arguments or ragged inputs.
+	DynamicOrOther,
+}
+
+impl InputClass {
+	/// Classifies the input by its size.
+	///
+	/// # Arguments
+	///
+	/// * `input` - The transaction input.
+	///
+	pub fn classify(input: &[u8]) -> InputClass {
+		match input.len() {
+			0 => InputClass::Empty,
+			4 => InputClass::SelectorOnly,
+			36 => InputClass::OneWord,
+			68 => InputClass::TwoWords,
+			100 => InputClass::ThreeWords,
+			_ => InputClass::DynamicOrOther,
+		}
+	}
+}
+
+/// Returns the chain id the transaction was signed for, `None` for pre EIP-155 transactions.
+///
+/// The chain id is recovered from the RLP encoded `raw` transaction, requiring the `rlp` feature.
+/// See `decode_raw` for the other fields.
+///
+/// # Arguments
+///
+/// * `tx` - The transaction with the `raw` field.
+///
+#[cfg(feature = "rlp")]
+pub fn chain_id(tx: &Transaction) -> Option<u64> {
+	let raw = &tx.raw.as_ref()?.0;
+	match raw.first() {
+		// EIP-2718 typed transaction, the chain id is the first field.
+		Some(first) if *first <= 0x7f => rlp_u64(&rlp::Rlp::new(&raw[1..]).at(0).ok()?),
+		// Legacy transaction, the chain id is encoded in `v` by EIP-155.
+		Some(_) => {
+			let v = rlp_u64(&rlp::Rlp::new(raw).at(6).ok()?)?;
+			if v >= 35 {
+				Some((v - 35) / 2)
+			} else {
+				None
+			}
+		}
+		None => None,
+	}
+}
+
+#[cfg(feature = "rlp")]
+fn rlp_u64(item: &rlp::Rlp) -> Option<u64> {
+	let data = item.data().ok()?;
+	if data.len() > 8 {
+		return None;
+	}
+	Some(data.iter().fold(0, |acc, it| (acc << 8) | u64::from(*it)))
+}
+
+/// Fields decoded from a signed raw transaction.
+#[cfg(feature = "rlp")]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedRawTx {
+	/// EIP-2718 transaction type, `None` for legacy transactions.
+	pub transaction_type: Option<u8>,
+	/// Transaction nonce.
+	pub nonce: U256,
+	/// Gas limit.
+	pub gas: U256,
+	/// Recipient, `None` for contract creation.
+	pub to: Option<H160>,
+	/// Ether value sent.
+	pub value: U256,
+	/// Transaction input.
+	pub input: Vec<u8>,
+}
+
+/// Decodes the `nonce`, `gas`, `to`, `value`, and `input` from a signed raw transaction.
+///
+/// Supports legacy transactions, and the EIP-2930 and EIP-1559 typed transactions.
+///
+/// # Arguments
+///
+/// * `raw` - RLP encoded signed transaction.
+///
+#[cfg(feature = "rlp")]
+pub fn decode_raw(raw: &[u8]) -> Result<DecodedRawTx, ERC20Error> {
+	// Position of the nonce, gas, to, value, and input fields for each transaction type.
+	let (transaction_type, payload, positions) = match raw.first() {
+		None => return Err(ERC20Error::UnexpectedEndOfData),
+		Some(0x01) => (Some(0x01), &raw[1..], [1, 3, 4, 5, 6]),
+		Some(0x02) => (Some(0x02), &raw[1..], [1, 4, 5, 6, 7]),
+		Some(first) if *first <= 0x7f => return Err(ERC20Error::UnexpectedType),
+		Some(_) => (None, raw, [0, 2, 3, 4, 5]),
+	};
+	let fields = rlp::Rlp::new(payload);
+	if !fields.is_list() {
+		return Err(ERC20Error::UnexpectedType);
+	}
+	let field = |index: usize| rlp_data(&fields, index);
+	let to = field(positions[2])?;
+	Ok(DecodedRawTx {
+		transaction_type,
+		nonce: rlp_u256(field(positions[0])?)?,
+		gas: rlp_u256(field(positions[1])?)?,
+		to: match to.len() {
+			0 => None,
+			20 => Some(H160::from_slice(to)),
+			_ => return Err(ERC20Error::UnexpectedSize),
+		},
+		value: rlp_u256(field(positions[3])?)?,
+		input: field(positions[4])?.to_vec(),
+	})
+}
+
+#[cfg(feature = "rlp")]
+fn rlp_data<'a>(fields: &rlp::Rlp<'a>, index: usize) -> Result<&'a [u8], ERC20Error> {
+	fields.at(index).and_then(|it| it.data()).map_err(|_| ERC20Error::UnexpectedType)
+}
+
+#[cfg(feature = "rlp")]
+fn rlp_u256(data: &[u8]) -> Result<U256, ERC20Error> {
+	if data.len() > 32 {
+		return Err(ERC20Error::UnexpectedSize);
+	}
+	Ok(U256::from_big_endian(data))
+}
+
+/// Sums the value transferred for each contract.
+///
+/// Ether transfers are accumulated under `ContractAddress::default()`, the unidentified zero address.
+///
+/// # Arguments
+///
+/// * `transfers` - Transfers to be accumulated.
+///
+pub fn volume_by_contract(
+	transfers: &[TransactionAndTransferType],
+) -> Result<HashMap<ContractAddress, U256>, ERC20Error> {
+	let mut resp: HashMap<ContractAddress, U256> = HashMap::new();
+	for transfer in transfers {
+		let contract = match transfer.contract() {
+			Some(address) => address.into(),
+			None => ContractAddress::default(),
+		};
+		let volume = resp.entry(contract).or_default();
+		*volume = volume.checked_add(transfer.try_value()?).ok_or(ERC20Error::Overflow)?;
+	}
+	Ok(resp)
+}
+
+/// Sums the value transferred for each contract, like `volume_by_contract`, ordered by the contract.
+///
+/// The order is deterministic, so the serialized output is reproducible.
+///
+/// # Arguments
+///
+/// * `transfers` - Transfers to be accumulated.
+///
+pub fn volume_by_contract_sorted(
+	transfers: &[TransactionAndTransferType],
+) -> Result<BTreeMap<ContractAddress, U256>, ERC20Error> {
+	Ok(volume_by_contract(transfers)?.into_iter().collect())
+}
+
+/// Counts the transfers sent by each address, ordered by the address.
+///
+/// Transfers that cannot be decoded are skipped.
+///
+/// # Arguments
+///
+/// * `transfers` - Transfers to be counted.
+///
+pub fn transfer_count_by_sender(transfers: &[TransactionAndTransferType]) -> BTreeMap<H160, usize> {
+	let mut resp: BTreeMap<H160, usize> = BTreeMap::new();
+	for sender in transfers.iter().filter_map(|it| it.try_from_addr().ok()) {
+		*resp.entry(sender).or_default() += 1;
+	}
+	resp
+}
+
+/// Writes the transfers as CSV, a header row followed by a line per transfer.
+///
+/// The columns are `tx_hash`, `from`, `to`, `value`, `contract`, and `block_number`.
+/// The contract is the token symbol, the address for unidentified tokens, or `ETH` for Ether transfers.
+/// Transfers that cannot be decoded are skipped with a warning.
+///
+/// # Arguments
+///
+/// * `transfers` - Transfers to be written.
+/// * `w` - Destination of the CSV.
+///
+pub fn write_csv<W: Write>(transfers: &[TransactionAndTransferType], mut w: W) -> Result<(), ERC20Error> {
+	let write_failed = |err: std::io::Error| ERC20Error::WriteFailed(err.to_string());
+	writeln!(w, "tx_hash,from,to,value,contract,block_number").map_err(write_failed)?;
+	for transfer in transfers {
+		let (from_v, to_v, value_v, contract) = match transfer.get_transfer_details() {
+			Ok(details) => details,
+			Err(err) => {
+				log::warn!("Skipping transfer {:?}: {:?}", transfer.transaction.hash, err);
+				continue;
+			}
+		};
+		let contract = match contract.map(ContractAddress::from) {
+			None => "ETH".to_string(),
+			Some(ContractAddress::Unidentified(address)) => encode_hex(&address.0),
+			Some(token) => token.symbol().unwrap_or_default().to_string(),
+		};
+		let block_number = transfer.transaction.block_number.map(|it| it.to_string()).unwrap_or_default();
+		writeln!(
+			w, "{},{},{},{},{},{}",
+			encode_hex(&transfer.transaction.hash.0), encode_hex(&from_v.0), encode_hex(&to_v.0), value_v, contract,
+			block_number,
+		).map_err(write_failed)?;
+	}
+	Ok(())
+}
+
+/// Returns the addresses referenced by the transaction, with no duplicates.
+///
+/// It includes the sender, the recipient, and the address arguments of the recognized ERC20 calls.
+///
+/// # Arguments
+///
+/// * `tx` - The transaction to be checked.
+///
+pub fn referenced_addresses(tx: &Transaction) -> Vec<H160> {
+	let mut resp = vec![tx.from];
+	if let Some(to) = tx.to {
+		resp.push(to);
+		let address_arguments = match ERC20Method::from(tx.input.0.clone()) {
+			ERC20Method::Transfer | ERC20Method::Approve | ERC20Method::BalanceOf | ERC20Method::Send => 1,
+			ERC20Method::TransferFrom | ERC20Method::Allowance => 2,
+			_ => 0,
+		};
+		let mut decoder: BytesToFixedNumber = tx.input.clone().into();
+		if decoder.skip(4).is_ok() {
+			for _ in 0..address_arguments {
+				match decoder.next_h160() {
+					Ok(address) => resp.push(address),
+					Err(_) => break,
+				}
+			}
+		}
+	}
+	let mut seen = HashSet::new();
+	resp.retain(|it| seen.insert(*it));
+	resp
+}
+
+/// Estimates the intrinsic gas for the calldata, 4 per zero byte and 16 per non-zero byte since Istanbul.
+///
+/// # Arguments
+///
+/// * `input` - The transaction input.
+///
+pub fn calldata_gas(input: &[u8]) -> u64 {
+	input.iter().map(|it| if *it == 0 { 4 } else { 16 }).sum()
+}
+
+/// Returns the key identifying replacement transactions, the sender and the nonce.
+///
+/// # Arguments
+///
+/// * `tx` - The transaction.
+///
+pub fn replacement_key(tx: &Transaction) -> (H160, U256) {
+	(tx.from, tx.nonce)
+}
+
+/// Finds the transactions replacing another with the same sender and nonce.
+///
+/// Returns each consecutive pair with the same key, the replaced transaction coming first, in the order they appear.
+///
+/// # Arguments
+///
+/// * `txs` - Transactions to be checked.
+///
+pub fn find_replacements(txs: &[Transaction]) -> Vec<(Transaction, Transaction)> {
+	let mut last_by_key: HashMap<(H160, U256), &Transaction> = HashMap::new();
+	let mut resp = Vec::new();
+	for tx in txs {
+		if let Some(previous) = last_by_key.insert(replacement_key(tx), tx) {
+			resp.push((previous.clone(), tx.clone()));
+		}
+	}
+	resp
+}
+
+/// Fetches the transaction from the node and parses it as a transfer, requiring the `async` feature.
+///
+/// # Arguments
+///
+/// * `web3` - Client for the node.
+/// * `hash` - Hash of the transaction.
+///
+#[cfg(feature = "async")]
+pub async fn fetch_transfer<T: web3::Transport>(
+	web3: &web3::Web3<T>, hash: H256,
+) -> Result<TransactionAndTransferType, ERC20Error> {
+	let transaction = web3.eth().transaction(web3::types::TransactionId::Hash(hash)).await
+		.map_err(|err| ERC20Error::RequestFailed(err.to_string()))?
+		.ok_or(ERC20Error::TransactionNotFound)?;
+	transaction.try_into()
+}
+
+/// Fetches the transactions concurrently and parses them as transfers, requiring the `async` feature.
+///
+/// The results come in the order the requests complete, so a slow request does not hold the others back,
+/// each one paired with its hash.
+///
+/// # Arguments
+///
+/// * `web3` - Client for the node.
+/// * `hashes` - Hashes of the transactions.
+/// * `concurrency` - Maximum number of requests running at the same time, at least one.
+///
+#[cfg(feature = "async")]
+pub async fn fetch_transfers<T: web3::Transport>(
+	web3: &web3::Web3<T>, hashes: Vec<H256>, concurrency: usize,
+) -> Vec<(H256, Result<TransactionAndTransferType, ERC20Error>)> {
+	use futures::stream::{
+		self,
+		StreamExt,
+	};
+
+	stream::iter(hashes)
+		.map(|hash| async move { (hash, fetch_transfer(web3, hash).await) })
+		.buffer_unordered(concurrency.max(1))
+		.collect()
+		.await
+}
+
+/// Checks if there is code deployed at the address, requiring the `async` feature.
+///
+/// # Arguments
+///
+/// * `web3` - Client for the node.
+/// * `addr` - The address to be checked.
+///
+#[cfg(feature = "async")]
+pub async fn is_contract<T: web3::Transport>(web3: &web3::Web3<T>, addr: H160) -> Result<bool, ERC20Error> {
+	let code = web3.eth().code(addr, None).await
+		.map_err(|err| ERC20Error::RequestFailed(err.to_string()))?;
+	Ok(!code.0.is_empty())
+}
+
+/// Checks if it is an EIP-1559 transaction.
+///
+/// The web3 transaction has no type field, so it checks the EIP-2718 type byte, `0x02`, of the `raw` transaction.
+/// Returns false for legacy transactions and when `raw` is not available.
+///
+/// # Arguments
+///
+/// * `tx` - The transaction to be checked.
+///
+pub fn is_eip1559(tx: &Transaction) -> bool {
+	tx.raw.as_ref().and_then(|raw| raw.0.first().cloned()) == Some(0x02)
+}
+
+/// Selector for the Disperse `disperseToken(address,address[],uint256[])` method.
+pub const DISPERSE_TOKEN_SELECTOR: [u8; 4] = [0xc7, 0x3a, 0x2d, 0x60];
+
+/// Decodes a Disperse `disperseToken` batch transfer returning the token, the recipients, and the values.
+///
+/// # Arguments
+///
+/// * `input` - The transaction input, including the method selector.
+///
+pub fn decode_disperse(input: &[u8]) -> Result<(H160, Vec<H160>, Vec<U256>), ERC20Error> {
+	if !input.starts_with(&DISPERSE_TOKEN_SELECTOR) {
+		return Err(ERC20Error::UnexpectedType);
+	}
+	let mut decoder: BytesToFixedNumber = input[4..].to_vec().into();
+	let token = decoder.next_h160()?;
+	let recipients = decoder.next_h160_array()?;
+	let values = decoder.next_u256_array()?;
+	if recipients.len() != values.len() {
+		return Err(ERC20Error::UnexpectedSize);
+	}
+	Ok((token, recipients, values))
+}
+
+/// Decodes an ERC-777 `send(address,uint256,bytes)` call returning the recipient, the amount, and the data.
+///
+/// # Arguments
+///
+/// * `input` - The transaction input, including the method selector.
+///
+pub fn decode_erc777_send(input: &[u8]) -> Result<(H160, U256, Vec<u8>), ERC20Error> {
+	if ERC20Method::from(input.to_vec()) != ERC20Method::Send {
+		return Err(ERC20Error::UnexpectedType);
+	}
+	let mut decoder: BytesToFixedNumber = input[4..].to_vec().into();
+	let to = decoder.next_h160()?;
+	let amount = decoder.next_u256()?;
+	let data = decoder.next_bytes()?;
+	Ok((to, amount, data))
+}
+
+/// Selector of the Uniswap V2 router `swapExactTokensForTokens(uint256,uint256,address[],address,uint256)` method.
+pub const SWAP_EXACT_TOKENS_FOR_TOKENS_SELECTOR: [u8; 4] = [0x38, 0xed, 0x17, 0x39];
+
+/// Parameters of a Uniswap V2 router swap, implying the transfer of `amount_in` of the first token in the path.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwapParams {
+	/// Amount of the input token sent by the caller.
+	pub amount_in: U256,
+	/// Tokens swapped through, from the input to the output token.
+	pub path: Vec<H160>,
+	/// Recipient of the output token.
+	pub to: H160,
+}
+
+/// Decodes a Uniswap V2 router `swapExactTokensForTokens` call.
+///
+/// # Arguments
+///
+/// * `input` - The transaction input, including the method selector.
+///
+pub fn decode_swap_exact_tokens(input: &[u8]) -> Result<SwapParams, ERC20Error> {
+	if !input.starts_with(&SWAP_EXACT_TOKENS_FOR_TOKENS_SELECTOR) {
+		return Err(ERC20Error::UnexpectedType);
+	}
+	let mut decoder: BytesToFixedNumber = input[4..].to_vec().into();
+	let amount_in = decoder.next_u256()?;
+	// Minimum amount of the output token.
+	decoder.next_u256()?;
+	let path = decoder.next_h160_array()?;
+	let to = decoder.next_h160()?;
+	Ok(SwapParams { amount_in, path, to })
+}
+
+/// Selector of the Gnosis Safe `execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)` method.
+pub const SAFE_EXEC_TRANSACTION_SELECTOR: [u8; 4] = [0x6a, 0x76, 0x12, 0x02];
+
+/// Decodes a Gnosis Safe `execTransaction` call returning the inner recipient, value, and data.
+///
+/// The inner data can be parsed again, e.g. as an ERC20 `transfer`, when the recipient is a token contract.
+///
+/// # Arguments
+///
+/// * `input` - The transaction input, including the method selector.
+///
+pub fn decode_safe_exec(input: &[u8]) -> Result<(H160, U256, Vec<u8>), ERC20Error> {
+	if !input.starts_with(&SAFE_EXEC_TRANSACTION_SELECTOR) {
+		return Err(ERC20Error::UnexpectedType);
+	}
+	let mut decoder: BytesToFixedNumber = input[4..].to_vec().into();
+	let to = decoder.next_h160()?;
+	let value = decoder.next_u256()?;
+	let data = decoder.next_bytes()?;
+	Ok((to, value, data))
+}
+
+/// Returns the string field of an Etherscan transaction, `None` if it is missing or empty.
+fn etherscan_field<'a>(value: &'a serde_json::Value, field: &str) -> Option<&'a str> {
+	value.get(field).and_then(|it| it.as_str()).filter(|it| !it.is_empty())
+}
+
+/// Returns the decimal number field of an Etherscan transaction, `None` if it is missing or empty.
+fn etherscan_number(value: &serde_json::Value, field: &str) -> Result<Option<U256>, ERC20Error> {
+	etherscan_field(value, field)
+		.map(|it| U256::from_dec_str(it).map_err(|_| ERC20Error::UnexpectedType))
+		.transpose()
+}
+
+/// Returns the hex hash field of an Etherscan transaction, `None` if it is missing or empty.
+fn etherscan_hash(value: &serde_json::Value, field: &str, size: usize) -> Result<Option<Vec<u8>>, ERC20Error> {
+	match etherscan_field(value, field) {
+		None => Ok(None),
+		Some(it) => {
+			let bytes = decode_hex(it)?;
+			if bytes.len() != size {
+				return Err(ERC20Error::UnexpectedSize);
+			}
+			Ok(Some(bytes))
+		}
+	}
+}
+
+/// Returns the decimal `u64` field of an Etherscan transaction, `None` if it is missing or empty.
+///
+/// Returns `Overflow` if the number does not fit in a `u64`.
+fn etherscan_u64(value: &serde_json::Value, field: &str) -> Result<Option<U64>, ERC20Error> {
+	etherscan_number(value, field)?
+		.map(|it| if it > U256::from(u64::max_value()) { Err(ERC20Error::Overflow) } else { Ok(it.low_u64().into()) })
+		.transpose()
+}
+
+/// Parses a transaction in the format returned by the Etherscan API.
+///
+/// Etherscan encodes the numbers, like `value`, `nonce`, and `blockNumber`, as decimal strings,
+/// and a contract creation with an empty `to`.
+/// Returns `Overflow` if the `blockNumber` or `transactionIndex` does not fit in a `u64`.
+///
+/// # Arguments
+///
+/// * `value` - The Etherscan transaction JSON.
+///
+pub fn from_etherscan_json(value: &serde_json::Value) -> Result<TransactionAndTransferType, ERC20Error> {
+	let hash = etherscan_hash(value, "hash", 32)?.ok_or(ERC20Error::UnexpectedType)?;
+	let from = etherscan_hash(value, "from", 20)?.ok_or(ERC20Error::UnexpectedType)?;
+	let input = match etherscan_field(value, "input") {
+		Some(it) => decode_hex(it)?,
+		None => Vec::new(),
+	};
+	let tx = Transaction {
+		hash: H256::from_slice(&hash),
+		nonce: etherscan_number(value, "nonce")?.unwrap_or_default(),
+		block_hash: etherscan_hash(value, "blockHash", 32)?.map(|it| H256::from_slice(&it)),
+		block_number: etherscan_u64(value, "blockNumber")?,
+		transaction_index: etherscan_u64(value, "transactionIndex")?,
+		from: H160::from_slice(&from),
+		to: etherscan_hash(value, "to", 20)?.map(|it| H160::from_slice(&it)),
+		value: etherscan_number(value, "value")?.unwrap_or_default(),
+		gas_price: etherscan_number(value, "gasPrice")?.unwrap_or_default(),
+		gas: etherscan_number(value, "gas")?.unwrap_or_default(),
+		input: Bytes(input),
+		..Default::default()
+	};
+	TransactionAndTransferType::try_from(tx)
+}
+
+/// Checks if a contract creation deploys what looks like an ERC20 token.
+///
+/// It is a heuristic looking for the `transfer`, `balanceOf`, and `totalSupply` selectors anywhere in the init code,
+/// so contracts only calling a token, or any bytecode containing those byte sequences by chance, are false positives.
+///
+/// # Arguments
+///
+/// * `tx` - The transaction.
+///
+pub fn creation_looks_like_erc20(tx: &Transaction) -> bool {
+	if tx.to.is_some() {
+		return false;
+	}
+	let code = &tx.input.0;
+	vec![ERC20Method::Transfer, ERC20Method::BalanceOf, ERC20Method::TotalSupply].into_iter().all(|method| {
+		let selector: [u8; 4] = method.try_into().unwrap();
+		code.windows(4).any(|window| window == selector)
+	})
+}
+
+/// Returns the method selector as a `0x` prefixed hex string, `None` if the input is shorter than 4 bytes.
+///
+/// # Arguments
+///
+/// * `tx` - The transaction.
+///
+pub fn selector_hex(tx: &Transaction) -> Option<String> {
+	if tx.input.0.len() < 4 {
+		None
+	} else {
+		Some(encode_hex(&tx.input.0[..4]))
+	}
+}
+
+/// Sorts the transfers in execution order, by the transaction index with the pending ones last.
+///
+/// Transfers with the same index are sorted by the transaction hash, so the order is deterministic.
+///
+/// # Arguments
+///
+/// * `transfers` - Transfers to be sorted.
+///
+pub fn sort_execution_order(transfers: &mut [TransactionAndTransferType]) {
+	transfers.sort_by_key(|it| (it.transaction_index().is_none(), it.transaction_index(), it.tx_hash()));
+}
+
+/// Lazily parses the logs as `Transfer` events, with no intermediate collection.
+///
+/// Logs of other events yield `UnexpectedType`, so they can be filtered out by the caller.
+///
+/// # Arguments
+///
+/// * `logs` - The logs to be parsed.
+///
+pub fn transfer_logs<I: Iterator<Item = Log>>(logs: I) -> impl Iterator<Item = Result<LogTransfer, ERC20Error>> {
+	logs.map(LogTransfer::try_from)
+}
+
+/// Parses the `Transfer` event logs grouped by the transaction hash, skipping the other logs.
+///
+/// The transfers keep the order of the logs, pending logs with no transaction hash are grouped under the zero hash.
+///
+/// # Arguments
+///
+/// * `logs` - The logs to be parsed, e.g. from an `eth_getLogs` response.
+///
+pub fn group_transfer_logs(logs: Vec<Log>) -> HashMap<H256, Vec<LogTransfer>> {
+	let mut resp: HashMap<H256, Vec<LogTransfer>> = HashMap::new();
+	for transfer in transfer_logs(logs.into_iter()).filter_map(Result::ok) {
+		resp.entry(transfer.tx_hash()).or_default().push(transfer);
+	}
+	resp
+}
+
+/// Disagreement between a calldata transfer and the log emitted for it, e.g. a token behind a proxy or charging a fee.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransferDiscrepancy {
+	/// The calldata transfer and the log disagree on the contract or the value.
+	#[serde(rename_all = "camelCase")]
+	Mismatch {
+		/// Hash of the transaction.
+		tx_hash: H256,
+		/// Contract invoked by the transaction.
+		calldata_contract: Option<H160>,
+		/// Contract emitting the log.
+		log_contract: H160,
+		/// Value in the transaction input.
+		calldata_value: U256,
+		/// Value in the log.
+		log_value: U256,
+	},
+	/// The calldata transfer cannot be decoded, only possible for deserialized values, so it matches no log.
+	#[serde(rename_all = "camelCase")]
+	Undecodable {
+		/// Hash of the transaction.
+		tx_hash: H256,
+		/// The error decoding the transfer.
+		error: ERC20Error,
+	},
+}
+
+/// Merges the transfers parsed from calldata and logs like `reconcile`, also reporting their discrepancies.
+///
+/// A calldata transfer with no matching log is discrepant with a log of the same transaction, sender, and recipient,
+/// disagreeing on the contract or the value. The calldata transfers that cannot be decoded are reported as well.
+///
+/// # Arguments
+///
+/// * `calldata` - Transfers parsed from the transactions input.
+/// * `logs` - Transfers parsed from the `Transfer` event logs.
+///
+pub fn reconcile_with_discrepancies(
+	calldata: Vec<TransactionAndTransferType>, logs: Vec<LogTransfer>,
+) -> (Vec<Box<dyn Transfer>>, Vec<TransferDiscrepancy>) {
+	let log_keys: HashSet<TransferKey> = logs.iter().filter_map(|it| it.dedup_key().ok()).collect();
+	let mut discrepancies = Vec::new();
+	for transfer in calldata.iter().filter(|it| it.contract().is_some()) {
+		let (from_v, to_v, value_v) = match transfer.get_from_to_value() {
+			Ok(it) => it,
+			Err(error) => {
+				discrepancies.push(TransferDiscrepancy::Undecodable { tx_hash: transfer.tx_hash(), error });
+				continue;
+			}
+		};
+		if log_keys.contains(&(transfer.tx_hash(), from_v, to_v, value_v, transfer.contract())) {
+			continue;
+		}
+		let log = logs.iter().find(|log| {
+			log.tx_hash() == transfer.tx_hash() && log.from() == from_v && log.to() == to_v
+		});
+		if let Some(log) = log {
+			discrepancies.push(TransferDiscrepancy::Mismatch {
+				tx_hash: transfer.tx_hash(),
+				calldata_contract: transfer.contract(),
+				log_contract: log.log().address,
+				calldata_value: value_v,
+				log_value: log.value(),
+			});
+		}
+	}
+	(reconcile(calldata, logs), discrepancies)
+}
+
+/// Merges the transfers parsed from calldata and logs, removing the duplicates.
+///
+/// Transfers with the same `dedup_key` are the same asset movement, the log version is kept since it is more
+/// authoritative. The log transfers come first, followed by the calldata transfers with no matching log.
+/// The transfers that cannot be decoded, only possible for deserialized values, are skipped.
+///
+/// # Arguments
+///
+/// * `calldata` - Transfers parsed from the transactions input.
+/// * `logs` - Transfers parsed from the `Transfer` event logs.
+///
+pub fn reconcile(calldata: Vec<TransactionAndTransferType>, logs: Vec<LogTransfer>) -> Vec<Box<dyn Transfer>> {
+	let log_keys: HashSet<TransferKey> = logs.iter().filter_map(|it| it.dedup_key().ok()).collect();
+	let mut resp: Vec<Box<dyn Transfer>> = logs.into_iter().map(|it| it.into_boxed()).collect();
+	for transfer in calldata {
+		if transfer.dedup_key().map_or(false, |key| !log_keys.contains(&key)) {
+			resp.push(transfer.into_boxed());
+		}
+	}
+	resp
 }
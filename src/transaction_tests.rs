@@ -1,10 +1,50 @@
 use crate::{
+	erc20::{
+		ContractAddress,
+		ERC20Method,
+	},
 	transfer::Transfer,
-	transaction::TransactionAndTransferType,
+	util::FixedNumberToBytes,
+	transaction::{
+		calldata_gas,
+		creation_looks_like_erc20,
+		decode_disperse,
+		decode_erc777_send,
+		decode_multicall,
+		decode_safe_exec,
+		decode_swap_exact_tokens,
+		filter_by_addresses,
+		find_replacements,
+		from_etherscan_json,
+		has_ragged_input,
+		input_word_count,
+		is_eip1559,
+		is_permit_transfer,
+		referenced_addresses,
+		selector_hex,
+		sort_execution_order,
+		transfer_count_by_sender,
+		volume_by_contract,
+		volume_by_contract_sorted,
+		write_csv,
+		FilterMode,
+		InputClass,
+		parse_trace_calls,
+		CallFrame,
+		ParsedTransaction,
+		SwapParams,
+		TransactionAndTransferType,
+		TransactionContractInvocation,
+		TransferRow,
+	},
 	ERC20Error,
 };
 use std::{
-	convert::TryInto,
+	collections::HashSet,
+	convert::{
+		TryFrom,
+		TryInto,
+	},
 	str::FromStr,
 };
 use web3::types::{
@@ -12,8 +52,36 @@ use web3::types::{
 	H160,
 	H256,
 	Transaction,
+	U256,
+	U64,
 };
 
+fn erc20_transfer(contract: ContractAddress, to: H160, value: U256) -> TransactionAndTransferType {
+	let input = FixedNumberToBytes::default()
+		.push_selector(ERC20Method::Transfer).unwrap()
+		.push_h160(&to)
+		.push_u256(&value)
+		.to_vec();
+	let transaction = Transaction {
+		hash: H256::random(),
+		from: H160::random(),
+		to: Some(contract.into()),
+		input: Bytes(input),
+		..Default::default()
+	};
+	TransactionAndTransferType::try_from(transaction).unwrap()
+}
+
+fn ether_transaction(from: H160, value: U256) -> Transaction {
+	Transaction {
+		hash: H256::random(),
+		from,
+		to: Some(H160::random()),
+		value,
+		..Default::default()
+	}
+}
+
 #[test]
 fn parse_no_transfer_transaction() {
 	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
@@ -62,3 +130,1185 @@ fn parse_erc20() {
 	let resp = resp.unwrap();
 	assert_eq!(transaction.from, resp.from());
 }
+
+#[test]
+fn boxed_erc20_transfer() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
+
+	let transaction = Transaction {
+		hash: H256::from_str("43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee").unwrap(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(serialized_str).unwrap()),
+		raw: None,
+	};
+
+	let resp: TransactionAndTransferType = transaction.clone().try_into().unwrap();
+	let transfers: Vec<Box<dyn Transfer>> = vec![resp.into_boxed()];
+	assert_eq!(1, transfers.len());
+	assert!(transfers[0].is_erc20());
+	assert_eq!(transaction.from, transfers[0].from());
+	assert_eq!(transaction.to, transfers[0].contract());
+}
+
+#[test]
+fn parse_truncated_erc20() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b";
+
+	let transaction = Transaction {
+		hash: H256::from_str("43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee").unwrap(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(serialized_str).unwrap()),
+		raw: None,
+	};
+
+	let resp: Result<TransactionAndTransferType, ERC20Error> = transaction.try_into();
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), resp);
+}
+
+#[test]
+fn unidentified_invocation_selector() {
+	let transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode("d0e30db0").unwrap()),
+		raw: None,
+	};
+
+	let invocation: TransactionContractInvocation = transaction.clone().into();
+	assert_eq!(TransactionContractInvocation::Other(transaction.clone()), invocation);
+	assert_eq!(Some([0xd0, 0xe3, 0x0d, 0xb0]), invocation.selector());
+
+	let mut short_transaction = transaction;
+	short_transaction.input = Bytes(vec![0xd0, 0xe3]);
+	let invocation: TransactionContractInvocation = short_transaction.into();
+	assert_eq!(None, invocation.selector());
+}
+
+#[test]
+fn parse_selector_only_input() {
+	let transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode("18160ddd").unwrap()),
+		raw: None,
+	};
+
+	let parsed: ParsedTransaction = transaction.clone().into();
+	assert_eq!(
+		ParsedTransaction::ContractInvocation(TransactionContractInvocation::ERC20(
+			ERC20Method::TotalSupply, transaction.clone(),
+		)),
+		parsed,
+	);
+
+	let resp: Result<TransactionAndTransferType, ERC20Error> = transaction.try_into();
+	assert_eq!(Err(ERC20Error::NoTransferTransaction), resp);
+}
+
+#[test]
+fn contract_creation_value() {
+	let transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: None,
+		value: U256::from(1000),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode("6080604052").unwrap()),
+		raw: None,
+	};
+
+	let parsed: ParsedTransaction = transaction.into();
+	assert_eq!(Some(U256::from(1000)), parsed.creation_value());
+	assert_eq!(None, ParsedTransaction::default().creation_value());
+}
+
+#[test]
+fn parse_nested_trace_transfers() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
+	let router = H160::random();
+	let token = H160::random();
+
+	let traces = vec![CallFrame {
+		from: H160::random(),
+		to: router,
+		input: hex::decode("38ed1739").unwrap(),
+		value: Default::default(),
+		calls: vec![CallFrame {
+			from: router,
+			to: token,
+			input: hex::decode(serialized_str).unwrap(),
+			value: Default::default(),
+			calls: vec![],
+		}],
+	}];
+
+	let resp = parse_trace_calls(H256::random(), &traces);
+	assert_eq!(1, resp.len());
+	assert_eq!(router, resp[0].from());
+	assert_eq!(Some(token), resp[0].contract());
+	assert_eq!(H160::from_str("6748f50f686bfbca6fe8ad62b22228b87f31ff2b").unwrap(), resp[0].to());
+}
+
+/// Encodes `multicall(bytes[])` calldata for the calls.
+fn multicall_input(calls: &[Vec<u8>]) -> Vec<u8> {
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_vec(&hex::decode("ac9650d8").unwrap());
+	encoder.push_u256(&U256::from(32));
+	encoder.push_u256(&U256::from(calls.len()));
+	let mut offset = 32 * calls.len();
+	for call in calls {
+		encoder.push_u256(&U256::from(offset));
+		offset += 32 + (call.len() + 31) / 32 * 32;
+	}
+	for call in calls {
+		encoder.push_u256(&U256::from(call.len()));
+		encoder.push_vec(call);
+		encoder.push_vec(&vec![0; (32 - call.len() % 32) % 32]);
+	}
+	encoder.into()
+}
+
+#[test]
+fn decode_multicall_transfers() {
+	let transfer = hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap();
+	let input = multicall_input(&[transfer, hex::decode("d0e30db0").unwrap()]);
+
+	let resp = decode_multicall(&input);
+	assert_eq!(Ok(vec![ERC20Method::Transfer, ERC20Method::UnidentifiedWith([0xd0, 0xe3, 0x0d, 0xb0])]), resp);
+
+	assert_eq!(Err(ERC20Error::UnexpectedType), decode_multicall(&input[1..]));
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), decode_multicall(&input[..input.len() - 64]));
+}
+
+#[test]
+fn filter_transfers_by_addresses() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
+	let sanctioned = H160::random();
+	let transfers: Vec<TransactionAndTransferType> = vec![sanctioned, H160::random()].into_iter()
+		.map(|from| {
+			let transaction = Transaction {
+				hash: H256::random(),
+				nonce: Default::default(),
+				block_hash: None,
+				block_number: None,
+				transaction_index: None,
+				from,
+				to: Some(H160::random()),
+				value: Default::default(),
+				gas_price: Default::default(),
+				gas: Default::default(),
+				input: Bytes(hex::decode(serialized_str).unwrap()),
+				raw: None,
+			};
+			TransactionAndTransferType::try_from(transaction).unwrap()
+		})
+		.collect();
+
+	let mut addresses = HashSet::new();
+	addresses.insert(sanctioned);
+
+	let included: Vec<TransactionAndTransferType> = filter_by_addresses(
+		transfers.clone().into_iter(), &addresses, FilterMode::Include,
+	).collect();
+	assert_eq!(1, included.len());
+	assert_eq!(Ok(sanctioned), included[0].try_from_addr());
+
+	let excluded: Vec<TransactionAndTransferType> = filter_by_addresses(
+		transfers.into_iter(), &addresses, FilterMode::Exclude,
+	).collect();
+	assert_eq!(1, excluded.len());
+	assert_ne!(Ok(sanctioned), excluded[0].try_from_addr());
+}
+
+#[test]
+fn transfer_input_word_count() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
+	let mut transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(serialized_str).unwrap()),
+		raw: None,
+	};
+	assert_eq!(Some(2), input_word_count(&transaction));
+	assert!(!has_ragged_input(&transaction));
+
+	transaction.input.0.pop();
+	assert_eq!(Some(1), input_word_count(&transaction));
+	assert!(has_ragged_input(&transaction));
+
+	transaction.input = Bytes(vec![0xa9]);
+	assert_eq!(None, input_word_count(&transaction));
+	assert!(!has_ragged_input(&transaction));
+}
+
+#[cfg(feature = "rlp")]
+#[test]
+fn transaction_chain_id() {
+	use crate::transaction::chain_id;
+
+	let mut transaction = Transaction::default();
+	assert_eq!(None, chain_id(&transaction));
+
+	// EIP-155 example transaction.
+	transaction.raw = Some(Bytes(hex::decode("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap()));
+	assert_eq!(Some(1), chain_id(&transaction));
+
+	// Pre EIP-155 transaction with `v` 27.
+	transaction.raw = Some(Bytes(hex::decode("c98080808080801b8080").unwrap()));
+	assert_eq!(None, chain_id(&transaction));
+
+	// EIP-1559 transaction for the chain 137.
+	transaction.raw = Some(Bytes(hex::decode("02c28189").unwrap()));
+	assert_eq!(Some(137), chain_id(&transaction));
+}
+
+#[test]
+fn approve_revoke() {
+	let spender = H160::random();
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_vec(&hex::decode("095ea7b3").unwrap());
+	encoder.push_h160(&spender);
+	encoder.push_u256(&U256::zero());
+
+	let mut transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(encoder.into()),
+		raw: None,
+	};
+	let invocation: TransactionContractInvocation = transaction.clone().into();
+	assert_eq!(Ok((spender, U256::zero())), invocation.approval());
+	assert!(invocation.is_revoke());
+
+	transaction.input.0[67] = 1;
+	let invocation: TransactionContractInvocation = transaction.clone().into();
+	assert!(!invocation.is_revoke());
+
+	transaction.input.0.truncate(40);
+	let invocation: TransactionContractInvocation = transaction.into();
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), invocation.approval());
+	assert!(!invocation.is_revoke());
+}
+
+#[test]
+fn approve_infinite() {
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_vec(&hex::decode("095ea7b3").unwrap());
+	encoder.push_h160(&H160::random());
+	encoder.push_u256(&U256::max_value());
+
+	let mut transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(encoder.into()),
+		raw: None,
+	};
+	let invocation: TransactionContractInvocation = transaction.clone().into();
+	assert!(invocation.is_infinite_approval());
+	assert!(!invocation.is_revoke());
+
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_vec(&hex::decode("095ea7b3").unwrap());
+	encoder.push_h160(&H160::random());
+	encoder.push_u256(&U256::from(1000));
+	transaction.input = Bytes(encoder.into());
+	let invocation: TransactionContractInvocation = transaction.into();
+	assert!(!invocation.is_infinite_approval());
+}
+
+#[test]
+fn volume_for_contracts() {
+	let mut transfers = vec![
+		erc20_transfer(ContractAddress::USDC, H160::random(), U256::from(10)),
+		erc20_transfer(ContractAddress::USDC, H160::random(), U256::from(5)),
+		erc20_transfer(ContractAddress::DAI, H160::random(), U256::from(7)),
+	];
+
+	let resp = volume_by_contract(&transfers).unwrap();
+	assert_eq!(2, resp.len());
+	assert_eq!(Some(&U256::from(15)), resp.get(&ContractAddress::USDC));
+	assert_eq!(Some(&U256::from(7)), resp.get(&ContractAddress::DAI));
+
+	let resp = volume_by_contract_sorted(&transfers).unwrap();
+	assert_eq!(
+		vec![(&ContractAddress::DAI, &U256::from(7)), (&ContractAddress::USDC, &U256::from(15))],
+		resp.iter().collect::<Vec<_>>(),
+	);
+
+	transfers.push(erc20_transfer(ContractAddress::USDC, H160::random(), U256::max_value()));
+	assert_eq!(Err(ERC20Error::Overflow), volume_by_contract(&transfers));
+}
+
+#[test]
+fn display_erc20_transfer() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000000000000000000003e8";
+	let transaction = Transaction {
+		hash: H256::from_str("43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee").unwrap(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::from_low_u64_be(1),
+		to: Some(ContractAddress::USDC.into()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(serialized_str).unwrap()),
+		raw: None,
+	};
+	let mut unknown = transaction.clone();
+	unknown.to = Some(H160::from_low_u64_be(2));
+	let transfer = TransactionAndTransferType::try_from(transaction).unwrap();
+
+	assert_eq!(
+		"ERC20 transfer 0x0000…0001 -> 0x6748…ff2b value 1000 (USDC) tx 0x43a5…bcee",
+		format!("{}", transfer),
+	);
+
+	let transfer = TransactionAndTransferType::try_from(unknown).unwrap();
+	assert_eq!(
+		"ERC20 transfer 0x0000…0001 -> 0x6748…ff2b value 1000 (0x0000…0002) tx 0x43a5…bcee",
+		format!("{}", transfer),
+	);
+}
+
+#[cfg(feature = "rlp")]
+#[test]
+fn decode_raw_legacy_transaction() {
+	use crate::transaction::decode_raw;
+
+	// EIP-155 example transaction.
+	let raw = hex::decode("f86c098504a817c800825208943535353535353535353535353535353535353535880de0b6b3a76400008025a028ef61340bd939bc2195fe537567866003e1a15d3c71ff63e1590620aa636276a067cbe9d8997f761aecb703304b3800ccf555c9f3dc64214b297fb1966a3b6d83").unwrap();
+	let resp = decode_raw(&raw).unwrap();
+	assert_eq!(None, resp.transaction_type);
+	assert_eq!(U256::from(9), resp.nonce);
+	assert_eq!(U256::from(21000), resp.gas);
+	assert_eq!(Some(H160::from_str("3535353535353535353535353535353535353535").unwrap()), resp.to);
+	assert_eq!(U256::exp10(18), resp.value);
+	assert!(resp.input.is_empty());
+
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), decode_raw(&[]));
+	assert_eq!(Err(ERC20Error::UnexpectedType), decode_raw(&[0x03, 0xc0]));
+}
+
+#[cfg(feature = "rlp")]
+#[test]
+fn decode_raw_eip1559_transaction() {
+	use crate::transaction::decode_raw;
+
+	// Chain 1, nonce 1, tips 1 and 2, gas 21000, creation with value 5 and input 0xa9059cbb, no access list.
+	let raw = hex::decode("02cf01010102825208800584a9059cbbc0").unwrap();
+	let resp = decode_raw(&raw).unwrap();
+	assert_eq!(Some(0x02), resp.transaction_type);
+	assert_eq!(U256::from(1), resp.nonce);
+	assert_eq!(U256::from(21000), resp.gas);
+	assert_eq!(None, resp.to);
+	assert_eq!(U256::from(5), resp.value);
+	assert_eq!(hex::decode("a9059cbb").unwrap(), resp.input);
+}
+
+#[test]
+fn transfer_from_referenced_addresses() {
+	let (sender, owner, recipient, token) = (H160::random(), H160::random(), H160::random(), H160::random());
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_vec(&hex::decode("23b872dd").unwrap());
+	encoder.push_h160(&owner);
+	encoder.push_h160(&recipient);
+	encoder.push_u256(&U256::from(1));
+
+	let mut transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: sender,
+		to: Some(token),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(encoder.into()),
+		raw: None,
+	};
+	assert_eq!(vec![sender, token, owner, recipient], referenced_addresses(&transaction));
+
+	transaction.input = Bytes(hex::decode("d0e30db0").unwrap());
+	assert_eq!(vec![sender, token], referenced_addresses(&transaction));
+
+	transaction.to = None;
+	assert_eq!(vec![sender], referenced_addresses(&transaction));
+}
+
+#[test]
+fn transfer_calldata_gas() {
+	let input = hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap();
+	assert_eq!(37, input.iter().filter(|it| **it == 0).count());
+	assert_eq!(37 * 4 + 31 * 16, calldata_gas(&input));
+	assert_eq!(0, calldata_gas(&[]));
+}
+
+#[test]
+fn erc20_transfer_details() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
+	let transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(ContractAddress::DAI.into()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(serialized_str).unwrap()),
+		raw: None,
+	};
+	let transfer = TransactionAndTransferType::try_from(transaction.clone()).unwrap();
+
+	assert_eq!(
+		Ok((
+			transaction.from,
+			H160::from_str("6748f50f686bfbca6fe8ad62b22228b87f31ff2b").unwrap(),
+			U256::from_dec_str("1000000000000000000000").unwrap(),
+			transaction.to,
+		)),
+		transfer.get_transfer_details(),
+	);
+}
+
+#[test]
+fn replacement_transactions() {
+	let sender = H160::random();
+	let transaction = |from: H160, nonce: u64| Transaction {
+		hash: H256::random(),
+		nonce: U256::from(nonce),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from,
+		to: Some(H160::random()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(vec![]),
+		raw: None,
+	};
+	let txs = vec![
+		transaction(sender, 1), transaction(sender, 2), transaction(H160::random(), 1), transaction(sender, 1),
+		transaction(sender, 1),
+	];
+
+	let resp = find_replacements(&txs);
+	assert_eq!(vec![(txs[0].clone(), txs[3].clone()), (txs[3].clone(), txs[4].clone())], resp);
+	assert!(find_replacements(&txs[..3]).is_empty());
+}
+
+#[test]
+fn transfer_to_row() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
+	let transaction = Transaction {
+		hash: H256::from_str("43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee").unwrap(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: Some(U64::from(11_000_000)),
+		transaction_index: None,
+		from: H160::from_low_u64_be(1),
+		to: Some(ContractAddress::TUSD.into()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(serialized_str).unwrap()),
+		raw: None,
+	};
+	let transfer = TransactionAndTransferType::try_from(transaction).unwrap();
+
+	assert_eq!(
+		Ok(TransferRow {
+			tx_hash: "0x43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee".to_string(),
+			from: "0x0000000000000000000000000000000000000001".to_string(),
+			to: "0x6748f50f686bfbca6fe8ad62b22228b87f31ff2b".to_string(),
+			value: "1000000000000000000000".to_string(),
+			contract: Some("0x0000000000085d4780b73119b644ae5ecd22b376".to_string()),
+			block_number: Some(11_000_000),
+		}),
+		transfer.to_row(),
+	);
+}
+
+#[test]
+fn known_token_transfer() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
+	let mut transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(ContractAddress::LINK.into()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(serialized_str).unwrap()),
+		raw: None,
+	};
+	assert!(TransactionAndTransferType::try_from(transaction.clone()).unwrap().is_known_token());
+
+	transaction.to = Some(H160::from_low_u64_be(1));
+	assert!(!TransactionAndTransferType::try_from(transaction).unwrap().is_known_token());
+}
+
+#[test]
+fn parsed_transaction_kind_tag() {
+	let transaction = Transaction {
+		hash: H256::from_str("43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee").unwrap(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: U256::from(1),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(vec![]),
+		raw: None,
+	};
+	let parsed = ParsedTransaction::EthereumTransfer(transaction);
+
+	let serialized = serde_json::to_value(&parsed).unwrap();
+	assert_eq!("ethereumTransfer", serialized["kind"]);
+	assert_eq!("0x43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee", serialized["hash"]);
+	assert_eq!(parsed, serde_json::from_value(serialized).unwrap());
+
+	let serialized = serde_json::to_value(&ParsedTransaction::default()).unwrap();
+	assert_eq!("other", serialized["kind"]);
+}
+
+#[test]
+fn ethereum_transfer_fixture() {
+	let (from, to, tx_hash) = (H160::random(), H160::random(), H256::random());
+	let transfer = TransactionAndTransferType::ethereum(from, to, U256::from(1000), tx_hash).into_boxed();
+
+	assert!(transfer.is_ethereum());
+	assert_eq!(from, transfer.from());
+	assert_eq!(to, transfer.to());
+	assert_eq!(U256::from(1000), transfer.value());
+	assert_eq!(None, transfer.contract());
+	assert_eq!(tx_hash, transfer.tx_hash());
+}
+
+#[test]
+fn eip1559_transaction() {
+	let mut transaction = Transaction::default();
+	assert!(!is_eip1559(&transaction));
+
+	transaction.raw = Some(Bytes(hex::decode("02cf01010102825208800584a9059cbbc0").unwrap()));
+	assert!(is_eip1559(&transaction));
+
+	transaction.raw = Some(Bytes(hex::decode("c98080808080801b8080").unwrap()));
+	assert!(!is_eip1559(&transaction));
+}
+
+#[test]
+fn fee_on_transfer_value() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
+	let mut transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::from_str("45804880De22913dAFE09f4980848ECE6EcbAf78").unwrap()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(serialized_str).unwrap()),
+		raw: None,
+	};
+	assert!(!TransactionAndTransferType::try_from(transaction.clone()).unwrap().value_is_authoritative());
+
+	transaction.to = Some(ContractAddress::USDC.into());
+	assert!(TransactionAndTransferType::try_from(transaction).unwrap().value_is_authoritative());
+
+	let ether = TransactionAndTransferType::ethereum(H160::random(), H160::random(), U256::from(1), H256::random());
+	assert!(ether.value_is_authoritative());
+}
+
+/// Encodes `disperseToken(address,address[],uint256[])` calldata.
+fn disperse_input(token: &H160, recipients: &[H160], values: &[U256]) -> Vec<u8> {
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_vec(&hex::decode("c73a2d60").unwrap());
+	encoder.push_h160(token);
+	encoder.push_u256(&U256::from(3 * 32));
+	encoder.push_u256(&U256::from(4 * 32 + 32 * recipients.len()));
+	encoder.push_u256(&U256::from(recipients.len()));
+	for recipient in recipients {
+		encoder.push_h160(recipient);
+	}
+	encoder.push_u256(&U256::from(values.len()));
+	for value in values {
+		encoder.push_u256(value);
+	}
+	encoder.into()
+}
+
+#[test]
+fn decode_disperse_token() {
+	let token = H160::random();
+	let recipients = vec![H160::random(), H160::random()];
+	let values = vec![U256::from(1), U256::from(2)];
+
+	let input = disperse_input(&token, &recipients, &values);
+	assert_eq!(Ok((token, recipients.clone(), values.clone())), decode_disperse(&input));
+
+	let input = disperse_input(&token, &recipients, &values[..1]);
+	assert_eq!(Err(ERC20Error::UnexpectedSize), decode_disperse(&input));
+
+	assert_eq!(Err(ERC20Error::UnexpectedType), decode_disperse(&input[4..]));
+}
+
+#[test]
+fn invocation_methods() {
+	let transaction = |input: &str| Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(input).unwrap()),
+		raw: None,
+	};
+	let invocations: Vec<TransactionContractInvocation> = vec!["18160ddd", "d0e30db0", "70a08231"].into_iter()
+		.map(|input| transaction(input).into())
+		.collect();
+
+	let methods: Vec<ERC20Method> = invocations.iter().filter_map(Option::<ERC20Method>::from).collect();
+	assert_eq!(vec![ERC20Method::TotalSupply, ERC20Method::BalanceOf], methods);
+}
+
+#[test]
+fn zero_value_ether_transfer() {
+	let mut transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: U256::zero(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(vec![]),
+		raw: None,
+	};
+	assert_eq!(ParsedTransaction::Other(transaction.clone()), ParsedTransaction::from(transaction.clone()));
+	assert_eq!(
+		ParsedTransaction::EthereumTransfer(transaction.clone()),
+		ParsedTransaction::parse(transaction.clone(), true),
+	);
+	let resp = TransactionAndTransferType::try_from(transaction.clone());
+	assert_eq!(Err(ERC20Error::NoTransferTransaction), resp);
+	let transfer = TransactionAndTransferType::try_from_with(transaction.clone(), true).unwrap();
+	assert_eq!(Ok((transaction.from, transaction.to.unwrap(), U256::zero())), transfer.get_from_to_value());
+
+	transaction.value = U256::from(1);
+	assert_eq!(ParsedTransaction::EthereumTransfer(transaction.clone()), ParsedTransaction::from(transaction.clone()));
+	let transfer = TransactionAndTransferType::try_from(transaction.clone()).unwrap();
+	assert_eq!(Ok((transaction.from, transaction.to.unwrap(), U256::from(1))), transfer.get_from_to_value());
+}
+
+#[test]
+fn transfer_selector_hex() {
+	let mut transaction = Transaction {
+		input: Bytes(hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b").unwrap()),
+		..Default::default()
+	};
+	assert_eq!(Some("0xa9059cbb".to_string()), selector_hex(&transaction));
+
+	transaction.input = Bytes(vec![0xa9, 0x05, 0x9c]);
+	assert_eq!(None, selector_hex(&transaction));
+}
+
+#[test]
+fn transfers_execution_order() {
+	let transfer = |index: Option<u64>, hash: u64| {
+		let transaction = Transaction {
+			hash: H256::from_low_u64_be(hash),
+			transaction_index: index.map(U64::from),
+			..ether_transaction(H160::random(), U256::from(1))
+		};
+		TransactionAndTransferType::try_from(transaction).unwrap()
+	};
+	let mut transfers = vec![
+		transfer(None, 1), transfer(Some(2), 2), transfer(Some(1), 4), transfer(Some(1), 3), transfer(Some(0), 5),
+	];
+
+	sort_execution_order(&mut transfers);
+	let hashes: Vec<H256> = transfers.iter().map(|it| it.tx_hash()).collect();
+	assert_eq!(vec![5, 3, 4, 2, 1].into_iter().map(H256::from_low_u64_be).collect::<Vec<H256>>(), hashes);
+}
+
+#[test]
+fn decode_safe_exec_transaction() {
+	let token = H160::random();
+	let data = hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap();
+
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_vec(&hex::decode("6a761202").unwrap());
+	encoder.push_h160(&token);
+	encoder.push_u256(&U256::zero());
+	encoder.push_u256(&U256::from(10 * 32));
+	for _ in 0..4 {
+		encoder.push_u256(&U256::zero());
+	}
+	encoder.push_h160(&H160::zero());
+	encoder.push_h160(&H160::zero());
+	encoder.push_u256(&U256::from(10 * 32 + 32 + 96));
+	encoder.push_u256(&U256::from(data.len()));
+	encoder.push_vec(&data);
+	encoder.push_vec(&[0u8; 28]);
+	encoder.push_u256(&U256::zero());
+	let input: Vec<u8> = encoder.into();
+
+	let (to, value, inner) = decode_safe_exec(&input).unwrap();
+	assert_eq!(token, to);
+	assert_eq!(U256::zero(), value);
+	assert_eq!(data, inner);
+	assert_eq!(ERC20Method::Transfer, ERC20Method::from(inner));
+
+	assert_eq!(Err(ERC20Error::UnexpectedType), decode_safe_exec(&data));
+}
+
+#[test]
+fn parse_etherscan_json() {
+	let value = serde_json::json!({
+		"blockNumber": "11000000",
+		"hash": "0x43a5d6853c6a9ed3101dd335eafe2542f7c3af4e483d769de3b661816ed6bcee",
+		"nonce": "7",
+		"transactionIndex": "3",
+		"from": "0x6748f50f686bfbca6fe8ad62b22228b87f31ff2b",
+		"to": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+		"value": "0",
+		"gas": "60000",
+		"gasPrice": "20000000000",
+		"input": "0xa9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000"
+	});
+	let transfer = from_etherscan_json(&value).unwrap();
+	let (from, to, amount, contract) = transfer.get_transfer_details().unwrap();
+	assert_eq!(H160::from_str("6748f50f686bfbca6fe8ad62b22228b87f31ff2b").unwrap(), from);
+	assert_eq!(from, to);
+	assert_eq!(U256::from_dec_str("1000000000000000000000").unwrap(), amount);
+	assert_eq!(Some(H160::from_str("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap()), contract);
+	assert_eq!(Some(U64::from(11_000_000)), transfer.block_number());
+
+	let mut oversized = value.clone();
+	oversized["blockNumber"] = serde_json::json!("18446744073709551616");
+	assert_eq!(Err(ERC20Error::Overflow), from_etherscan_json(&oversized).map(|_| ()));
+	let mut oversized = value.clone();
+	oversized["transactionIndex"] = serde_json::json!("340282366920938463463374607431768211456");
+	assert_eq!(Err(ERC20Error::Overflow), from_etherscan_json(&oversized).map(|_| ()));
+	let mut largest = value;
+	largest["blockNumber"] = serde_json::json!("18446744073709551615");
+	assert_eq!(Some(U64::from(u64::max_value())), from_etherscan_json(&largest).unwrap().block_number());
+
+	let value = serde_json::json!({
+		"hash": "0x43a5d6853c6a9ed3101dd335eafe2542f7c3af4e483d769de3b661816ed6bcee",
+		"from": "0x6748f50f686bfbca6fe8ad62b22228b87f31ff2b",
+		"to": "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+		"value": "0x10"
+	});
+	assert_eq!(Err(ERC20Error::UnexpectedType), from_etherscan_json(&value).map(|_| ()));
+}
+
+#[test]
+fn detect_erc20_creation() {
+	let creation = |code: &str, to: Option<H160>| Transaction {
+		to,
+		input: Bytes(hex::decode(code).unwrap()),
+		..Default::default()
+	};
+
+	let code = "608060405263a9059cbb811461004057806370a082311461005057806318160ddd14610060575b600080fd";
+	assert!(creation_looks_like_erc20(&creation(code, None)));
+	assert!(!creation_looks_like_erc20(&creation(code, Some(H160::random()))));
+	assert!(!creation_looks_like_erc20(&creation("608060405263a9059cbb811461004057", None)));
+}
+
+#[test]
+fn decode_uniswap_v2_swap() {
+	let path = vec![H160::random(), H160::random(), H160::random()];
+	let to = H160::random();
+
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_vec(&hex::decode("38ed1739").unwrap());
+	encoder.push_u256(&U256::from(1_000));
+	encoder.push_u256(&U256::from(900));
+	encoder.push_u256(&U256::from(5 * 32));
+	encoder.push_h160(&to);
+	encoder.push_u256(&U256::from(1_600_000_000));
+	encoder.push_u256(&U256::from(path.len()));
+	for token in &path {
+		encoder.push_h160(token);
+	}
+	let input: Vec<u8> = encoder.into();
+
+	assert_eq!(
+		Ok(SwapParams { amount_in: U256::from(1_000), path, to }),
+		decode_swap_exact_tokens(&input),
+	);
+	assert_eq!(Err(ERC20Error::UnexpectedType), decode_swap_exact_tokens(&input[4..]));
+}
+
+#[test]
+fn transfer_to_token_contract() {
+	let usdc_address: H160 = ContractAddress::USDC.into();
+	assert!(erc20_transfer(ContractAddress::USDC, usdc_address, U256::from(1)).is_sent_to_token_contract());
+	assert!(!erc20_transfer(ContractAddress::USDC, H160::random(), U256::from(1)).is_sent_to_token_contract());
+
+	let ether = TransactionAndTransferType::ethereum(H160::random(), H160::random(), U256::from(1), H256::random());
+	assert!(!ether.is_sent_to_token_contract());
+}
+
+#[test]
+fn rebuild_transfer_calldata() {
+	let input = hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap();
+	let transaction = Transaction {
+		to: Some(ContractAddress::USDC.into()),
+		input: Bytes(input.clone()),
+		..Default::default()
+	};
+	let transfer = TransactionAndTransferType::try_from(transaction).unwrap();
+	assert_eq!(input, transfer.calldata());
+	assert_eq!(Ok(input), transfer.rebuild_calldata());
+
+	let ether = TransactionAndTransferType::ethereum(H160::random(), H160::random(), U256::from(1), H256::random());
+	assert_eq!(Ok(Vec::new()), ether.rebuild_calldata());
+}
+
+#[test]
+fn parse_erc777_send() {
+	let to = H160::random();
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_vec(&hex::decode("9bd9bbc6").unwrap());
+	encoder.push_h160(&to);
+	encoder.push_u256(&U256::from(1_000));
+	encoder.push_u256(&U256::from(3 * 32));
+	encoder.push_u256(&U256::from(3));
+	encoder.push_vec(&[0x01, 0x02, 0x03]);
+	encoder.push_vec(&[0; 29]);
+	let input: Vec<u8> = encoder.into();
+
+	assert_eq!(Ok((to, U256::from(1_000), vec![0x01, 0x02, 0x03])), decode_erc777_send(&input));
+
+	let from = H160::random();
+	let transaction = Transaction {
+		from,
+		to: Some(H160::random()),
+		input: Bytes(input.clone()),
+		..Default::default()
+	};
+	let transfer = TransactionAndTransferType::try_from(transaction).unwrap();
+	assert!(transfer.is_erc777_send());
+	assert_eq!(Ok((from, to, U256::from(1_000))), transfer.get_from_to_value());
+	assert_eq!(Ok(input), transfer.rebuild_calldata());
+}
+
+#[test]
+fn checked_deserialization() {
+	let transaction = Transaction {
+		to: Some(ContractAddress::USDC.into()),
+		input: Bytes(hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap()),
+		..Default::default()
+	};
+	let transfer = TransactionAndTransferType::try_from(transaction).unwrap();
+	let mut serialized = serde_json::to_value(&transfer).unwrap();
+	let deserialized: TransactionAndTransferType = serde_json::from_value(serialized.clone()).unwrap();
+	assert_eq!(Ok(transfer), deserialized.checked());
+
+	serialized["transaction"]["input"] = serde_json::json!("0xa9059cbb");
+	let malformed: TransactionAndTransferType = serde_json::from_value(serialized).unwrap();
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), malformed.checked());
+}
+
+#[test]
+fn classify_input() {
+	let transfer = hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap();
+	assert_eq!(InputClass::Empty, InputClass::classify(&[]));
+	assert_eq!(InputClass::SelectorOnly, InputClass::classify(&transfer[..4]));
+	assert_eq!(InputClass::OneWord, InputClass::classify(&transfer[..36]));
+	assert_eq!(InputClass::TwoWords, InputClass::classify(&transfer));
+	assert_eq!(InputClass::ThreeWords, InputClass::classify(&[transfer.clone(), vec![0; 32]].concat()));
+	assert_eq!(InputClass::DynamicOrOther, InputClass::classify(&transfer[..40]));
+}
+
+#[test]
+fn transfer_into_summary() {
+	let from = H160::random();
+	let transaction = Transaction {
+		hash: H256::random(),
+		from,
+		to: Some(ContractAddress::USDC.into()),
+		input: Bytes(hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap()),
+		..Default::default()
+	};
+	let transfer = TransactionAndTransferType::try_from(transaction.clone()).unwrap();
+	let mut serialized = serde_json::to_value(&transfer).unwrap();
+
+	let summary = transfer.into_summary().unwrap();
+	assert_eq!(transaction.hash, summary.tx_hash);
+	assert_eq!(from, summary.from);
+	assert_eq!(H160::from_str("6748f50f686bfbca6fe8ad62b22228b87f31ff2b").unwrap(), summary.to);
+	assert_eq!(U256::from_dec_str("1000000000000000000000").unwrap(), summary.value);
+	assert_eq!(transaction.to, summary.contract);
+
+	// A malformed transfer can still be deserialized.
+	serialized["transaction"]["input"] = serde_json::json!("0xa9059cbb");
+	let malformed: TransactionAndTransferType = serde_json::from_value(serialized).unwrap();
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), malformed.into_summary());
+}
+
+#[test]
+fn count_transfers_by_sender() {
+	let (a, b) = (H160::from_low_u64_be(1), H160::from_low_u64_be(2));
+	let transfers: Vec<TransactionAndTransferType> = vec![b, a, b].into_iter()
+		.map(|from| TransactionAndTransferType::try_from(ether_transaction(from, U256::from(1))).unwrap())
+		.collect();
+
+	let resp = transfer_count_by_sender(&transfers);
+	assert_eq!(vec![(&a, &1), (&b, &2)], resp.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn best_effort_recipient() {
+	let to = H160::from_str("6748f50f686bfbca6fe8ad62b22228b87f31ff2b").unwrap();
+	let input = hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap();
+	let transaction = Transaction {
+		to: Some(ContractAddress::USDC.into()),
+		input: Bytes(input.clone()),
+		..Default::default()
+	};
+	let transfer = TransactionAndTransferType::try_from(transaction).unwrap();
+	assert_eq!(Some(to), transfer.best_effort_to());
+
+	// A truncated value can only come from a deserialized transfer.
+	let mut serialized = serde_json::to_value(&transfer).unwrap();
+	serialized["transaction"]["input"] = serde_json::json!(format!("0x{}", hex::encode(&input[..40])));
+	let truncated: TransactionAndTransferType = serde_json::from_value(serialized.clone()).unwrap();
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), truncated.try_to());
+	assert_eq!(Some(to), truncated.best_effort_to());
+
+	serialized["transaction"]["input"] = serde_json::json!(format!("0x{}", hex::encode(&input[..20])));
+	let truncated: TransactionAndTransferType = serde_json::from_value(serialized).unwrap();
+	assert_eq!(None, truncated.best_effort_to());
+}
+
+#[test]
+fn detect_permit_transfer() {
+	let permit = [hex::decode("d505accf").unwrap(), vec![0; 7 * 32]].concat();
+	let transfer_from = [hex::decode("23b872dd").unwrap(), vec![0; 3 * 32]].concat();
+	let transfer = [hex::decode("a9059cbb").unwrap(), vec![0; 2 * 32]].concat();
+
+	assert!(is_permit_transfer(&multicall_input(&[permit.clone(), transfer_from.clone()])));
+	assert!(is_permit_transfer(&multicall_input(&[transfer_from.clone(), permit.clone(), transfer_from.clone()])));
+	assert!(!is_permit_transfer(&multicall_input(&[transfer_from.clone(), permit.clone()])));
+	assert!(!is_permit_transfer(&multicall_input(&[permit.clone(), transfer])));
+	assert!(!is_permit_transfer(&multicall_input(&[transfer_from.clone()])));
+	assert!(!is_permit_transfer(&transfer_from));
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn mock_erc20_transfer() {
+	let (contract, from, to) = (H160::random(), H160::random(), H160::random());
+	let transfer = TransactionAndTransferType::mock_erc20_transfer(contract, from, to, U256::from(1_000));
+	assert_eq!(Ok((from, to, U256::from(1_000), Some(contract))), transfer.get_transfer_details());
+	assert_eq!(Some(transfer.clone()), TransactionAndTransferType::try_from(Transaction {
+		from,
+		to: Some(contract),
+		input: Bytes(transfer.calldata()),
+		..Default::default()
+	}).ok());
+}
+
+#[test]
+fn transfer_from_spender() {
+	let (spender, owner, to) = (H160::random(), H160::random(), H160::random());
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_selector(ERC20Method::TransferFrom).unwrap().push_h160(&owner).push_h160(&to).push_u256(&U256::from(5));
+	let transaction = Transaction {
+		from: spender,
+		to: Some(ContractAddress::USDC.into()),
+		input: Bytes(encoder.into()),
+		..Default::default()
+	};
+	let transfer = TransactionAndTransferType::try_from(transaction).unwrap();
+	assert_eq!(Some(spender), transfer.spender());
+	assert_eq!(Ok(owner), transfer.try_from_addr());
+
+	let ether = TransactionAndTransferType::ethereum(spender, to, U256::from(1), H256::random());
+	assert_eq!(None, ether.spender());
+}
+
+#[test]
+fn export_csv() {
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_selector(ERC20Method::Transfer).unwrap().push_h160(&H160::from_low_u64_be(2)).push_u256(&U256::from(5));
+	let erc20 = TransactionAndTransferType::try_from(Transaction {
+		hash: H256::from_low_u64_be(1),
+		block_number: Some(U64::from(11_000_000)),
+		from: H160::from_low_u64_be(1),
+		to: Some(ContractAddress::USDC.into()),
+		input: Bytes(encoder.into()),
+		..Default::default()
+	}).unwrap();
+	let ether = TransactionAndTransferType::ethereum(
+		H160::from_low_u64_be(3), H160::from_low_u64_be(4), U256::from(7), H256::from_low_u64_be(2),
+	);
+
+	let mut output: Vec<u8> = Vec::new();
+	write_csv(&[erc20, ether], &mut output).unwrap();
+	let lines: Vec<String> = String::from_utf8(output).unwrap().lines().map(|it| it.to_string()).collect();
+	assert_eq!(vec![
+		"tx_hash,from,to,value,contract,block_number".to_string(),
+		format!("0x{:064x},0x{:040x},0x{:040x},5,USDC,11000000", 1, 1, 2),
+		format!("0x{:064x},0x{:040x},0x{:040x},7,ETH,", 2, 3, 4),
+	], lines);
+}
+
+#[test]
+fn misrouted_transfer() {
+	let input = hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap();
+	let parsed = |to: Option<H160>, input: &[u8]| ParsedTransaction::from(Transaction {
+		to,
+		input: Bytes(input.to_vec()),
+		..Default::default()
+	});
+
+	assert!(parsed(None, &input).looks_like_misrouted_transfer());
+	assert!(!parsed(None, &input[..36]).looks_like_misrouted_transfer());
+	assert!(!parsed(Some(H160::random()), &input).looks_like_misrouted_transfer());
+	assert!(!parsed(None, &[input.clone(), vec![0; 32]].concat()).looks_like_misrouted_transfer());
+}
+
+/// Node answering `eth_getTransactionByHash` with the known transactions, or `null`.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone)]
+struct MockTransport(Vec<Transaction>);
+
+#[cfg(feature = "async")]
+impl web3::Transport for MockTransport {
+	type Out = futures::future::Ready<web3::error::Result<web3::rpc::Value>>;
+
+	fn prepare(&self, method: &str, params: Vec<web3::rpc::Value>) -> (web3::RequestId, web3::rpc::Call) {
+		(0, web3::helpers::build_request(0, method, params))
+	}
+
+	fn send(&self, _id: web3::RequestId, request: web3::rpc::Call) -> Self::Out {
+		let hash: Option<H256> = match request {
+			web3::rpc::Call::MethodCall(web3::rpc::MethodCall { params: web3::rpc::Params::Array(params), .. }) =>
+				params.first().and_then(|it| serde_json::from_value(it.clone()).ok()),
+			_ => None,
+		};
+		let transaction = self.0.iter().find(|it| Some(it.hash) == hash);
+		futures::future::ready(Ok(serde_json::to_value(transaction).unwrap()))
+	}
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn fetch_transfers_concurrently() {
+	use crate::transaction::fetch_transfers;
+
+	let ether = Transaction {
+		hash: H256::from_low_u64_be(1),
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: U256::from(1),
+		..Default::default()
+	};
+	let other = Transaction {
+		hash: H256::from_low_u64_be(2),
+		to: Some(H160::random()),
+		..Default::default()
+	};
+	let missing = H256::from_low_u64_be(3);
+	let web3 = web3::Web3::new(MockTransport(vec![ether.clone(), other.clone()]));
+
+	let mut resp = futures::executor::block_on(fetch_transfers(&web3, vec![ether.hash, other.hash, missing], 2));
+	resp.sort_by_key(|(hash, _)| *hash);
+	assert_eq!(vec![
+		(ether.hash, TransactionAndTransferType::try_from(ether)),
+		(other.hash, Err(ERC20Error::NoTransferTransaction)),
+		(missing, Err(ERC20Error::TransactionNotFound)),
+	], resp);
+}
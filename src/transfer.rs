@@ -1,15 +1,26 @@
 ///! Ethereum transfer abstraction.
 
+use crate::{
+	erc20::{
+		parse_transfer_log,
+		ContractAddress,
+	},
+	util::u256_to_f64,
+	ERC20Error,
+};
 use serde::{
 	Deserialize,
 	Serialize,
 };
+use serde_json::json;
+use std::convert::TryFrom;
 use web3::types::{
 	BlockId,
 	BlockNumber,
 	H160,
 	H256,
 	Index,
+	Log,
 	TransactionId,
 	U64,
 	U256,
@@ -25,16 +36,38 @@ pub enum TransferType {
 	ERC20,
 }
 
+/// Direction of a transfer relative to an address.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+	/// The address is the recipient.
+	Incoming,
+	/// The address is the sender.
+	Outgoing,
+	/// The address is both the sender and the recipient.
+	SelfTransfer,
+	/// The address is neither the sender nor the recipient.
+	Unrelated,
+}
+
+/// Source of token prices, plugged by the user into `Transfer::value_usd`.
+pub trait Price {
+	/// Returns the price in USD of one unit of the token, `None` if it is not known.
+	///
+	/// Ether is priced as `ContractAddress::default()`, the unidentified zero address.
+	fn price_usd(&self, token: &ContractAddress) -> Option<f64>;
+}
+
+/// Transaction hash, sender, recipient, value, and contract identifying an asset movement.
+pub type TransferKey = (H256, H160, H160, U256, Option<H160>);
+
 /// Asset transfer abstraction.
+///
+/// The `try_*` accessors return the error decoding the transfer, the panicking `from`, `to`, and `value` getters
+/// are not available with the `strict` feature.
 pub trait Transfer {
-	/// Returns the sender of the transfer.
-	fn from(&self) -> H160;
-	/// Returns the recipient of the transfer.
-	fn to(&self) -> H160;
 	/// Returns the ERC20 contract address for ERC20 transfers.
 	fn contract(&self) -> Option<H160>;
-	/// Returns the value of the transfer.
-	fn value(&self) -> U256;
 	/// Returns the transaction hash for the transfer.
 	fn tx_hash(&self) -> H256;
 	/// Returns the block hash for the transfer, if available.
@@ -43,6 +76,107 @@ pub trait Transfer {
 	fn block_number(&self) -> Option<U64>;
 	/// Returns the transaction index for the transfer, if available.
 	fn transaction_index(&self) -> Option<Index>;
+	/// Returns the sender of the transfer, or the error decoding it.
+	fn try_from_addr(&self) -> Result<H160, ERC20Error>;
+	/// Returns the recipient of the transfer, or the error decoding it.
+	fn try_to(&self) -> Result<H160, ERC20Error>;
+	/// Returns the value of the transfer, or the error decoding it.
+	fn try_value(&self) -> Result<U256, ERC20Error>;
+
+	/// Returns the sender of the transfer, panics if it cannot be decoded.
+	#[cfg(not(feature = "strict"))]
+	fn from(&self) -> H160 {
+		match self.try_from_addr() {
+			Ok(it) => it,
+			Err(err) => panic!("Unexpected transfer for Transfer::from {:?}", err),
+		}
+	}
+
+	/// Returns the recipient of the transfer, panics if it cannot be decoded.
+	#[cfg(not(feature = "strict"))]
+	fn to(&self) -> H160 {
+		match self.try_to() {
+			Ok(it) => it,
+			Err(err) => panic!("Unexpected transfer for Transfer::to {:?}", err),
+		}
+	}
+
+	/// Returns the value of the transfer, panics if it cannot be decoded.
+	#[cfg(not(feature = "strict"))]
+	fn value(&self) -> U256 {
+		match self.try_value() {
+			Ok(it) => it,
+			Err(err) => panic!("Unexpected transfer for Transfer::value {:?}", err),
+		}
+	}
+
+	/// Returns the key identifying the same asset movement regardless of the source of the transfer,
+	/// or the error decoding it.
+	fn dedup_key(&self) -> Result<TransferKey, ERC20Error> {
+		Ok((self.tx_hash(), self.try_from_addr()?, self.try_to()?, self.try_value()?, self.contract()))
+	}
+
+	/// Checks if the tokens were minted, sent from the zero address, false if it cannot be decoded.
+	fn is_mint(&self) -> bool {
+		self.try_from_addr().map_or(false, |it| it.is_zero())
+	}
+
+	/// Checks if the tokens were burned, sent to the zero address, false if it cannot be decoded.
+	fn is_burn(&self) -> bool {
+		self.try_to().map_or(false, |it| it.is_zero())
+	}
+
+	/// Returns the direction of the transfer relative to the watched address, `Unrelated` if it cannot be decoded.
+	fn direction(&self, watched: H160) -> Direction {
+		let outgoing = self.try_from_addr().map_or(false, |it| it == watched);
+		let incoming = self.try_to().map_or(false, |it| it == watched);
+		match (outgoing, incoming) {
+			(true, true) => Direction::SelfTransfer,
+			(true, false) => Direction::Outgoing,
+			(false, true) => Direction::Incoming,
+			(false, false) => Direction::Unrelated,
+		}
+	}
+
+	/// Returns the value of the transfer in base units as a decimal string, panics like `value`.
+	#[cfg(not(feature = "strict"))]
+	fn value_decimal_string(&self) -> String {
+		self.value().to_string()
+	}
+
+	/// Returns the ERC20 contract as a `ContractAddress`, `None` for Ether transfers.
+	fn contract_token(&self) -> Option<ContractAddress> {
+		self.contract().map(ContractAddress::from)
+	}
+
+	/// Checks if the recipient is one of the known token contracts.
+	///
+	/// It is an offline approximation of the recipient being a contract, see `transaction::is_contract` for the exact answer.
+	/// Returns false if it cannot be decoded.
+	fn to_is_in_known_contract_set(&self) -> bool {
+		self.try_to().map_or(false, |it| !matches!(ContractAddress::from(it), ContractAddress::Unidentified(_)))
+	}
+
+	/// Returns the approximate value in USD, `None` if the price of the token is not known or it cannot be decoded.
+	///
+	/// # Arguments
+	///
+	/// * `prices` - Source of the token prices.
+	/// * `decimals` - Number of decimals for the token.
+	///
+	fn value_usd<P: Price>(&self, prices: &P, decimals: u8) -> Option<f64> where Self: Sized {
+		let token = self.contract_token().unwrap_or_default();
+		let value = self.try_value().ok()?;
+		prices.price_usd(&token).map(|price| u256_to_f64(value, decimals) * price)
+	}
+
+	/// Returns an identifier for the transfer, `{tx_hash}:{transaction_index}` or `{tx_hash}:pending`.
+	fn id(&self) -> String {
+		match self.transaction_index() {
+			Some(index) => format!("{:?}:{}", self.tx_hash(), index),
+			None => format!("{:?}:pending", self.tx_hash()),
+		}
+	}
 }
 
 impl dyn Transfer {
@@ -76,3 +210,133 @@ impl dyn Transfer {
 		}
 	}
 }
+
+/// ERC20 transfer obtained from a `Transfer` event log.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogTransfer {
+	log: Log,
+	from: H160,
+	to: H160,
+	value: U256,
+}
+
+impl TryFrom<Log> for LogTransfer {
+	type Error = ERC20Error;
+
+	fn try_from(log: Log) -> Result<Self, Self::Error> {
+		let (from, to, value) = parse_transfer_log(&log)?;
+		Ok(Self {
+			log,
+			from,
+			to,
+			value,
+		})
+	}
+}
+
+impl Transfer for LogTransfer {
+	fn contract(&self) -> Option<H160> {
+		Some(self.log.address)
+	}
+
+	/// Returns the transaction hash for the log, or zero for pending logs.
+	fn tx_hash(&self) -> H256 {
+		self.log.transaction_hash.unwrap_or_default()
+	}
+
+	fn block_hash(&self) -> Option<H256> {
+		self.log.block_hash
+	}
+
+	fn block_number(&self) -> Option<U64> {
+		self.log.block_number
+	}
+
+	fn transaction_index(&self) -> Option<Index> {
+		self.log.transaction_index
+	}
+
+	fn try_from_addr(&self) -> Result<H160, ERC20Error> {
+		Ok(self.from)
+	}
+
+	fn try_to(&self) -> Result<H160, ERC20Error> {
+		Ok(self.to)
+	}
+
+	fn try_value(&self) -> Result<U256, ERC20Error> {
+		Ok(self.value)
+	}
+}
+
+impl LogTransfer {
+	/// Returns the sender of the transfer, a log transfer is always decoded.
+	pub fn from(&self) -> H160 {
+		self.from
+	}
+
+	/// Returns the recipient of the transfer, a log transfer is always decoded.
+	pub fn to(&self) -> H160 {
+		self.to
+	}
+
+	/// Returns the value of the transfer, a log transfer is always decoded.
+	pub fn value(&self) -> U256 {
+		self.value
+	}
+
+	/// Returns the log the transfer was parsed from.
+	pub fn log(&self) -> &Log {
+		&self.log
+	}
+
+	/// Checks if the log was removed by a chain reorganization, so the transfer should be undone.
+	pub fn is_removed(&self) -> bool {
+		self.log.removed.unwrap_or(false)
+	}
+
+	/// Boxes the transfer as a `Transfer` trait object.
+	pub fn into_boxed(self) -> Box<dyn Transfer> {
+		Box::new(self)
+	}
+}
+
+/// Compact summary of a transfer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferSummary {
+	/// Transaction hash for the transfer.
+	pub tx_hash: H256,
+	/// Sender of the transfer.
+	pub from: H160,
+	/// Recipient of the transfer.
+	pub to: H160,
+	/// Value of the transfer.
+	pub value: U256,
+	/// ERC20 contract address, `None` for Ether transfers.
+	pub contract: Option<H160>,
+}
+
+impl TransferSummary {
+	/// Returns the JSON schema for the serialized summary.
+	///
+	/// Addresses, hashes, and values are serialized as `0x` prefixed hex strings.
+	pub fn json_schema() -> serde_json::Value {
+		let address = json!({"type": "string", "pattern": "^0x[0-9a-fA-F]{40}$"});
+		json!({
+			"$schema": "http://json-schema.org/draft-07/schema#",
+			"title": "TransferSummary",
+			"type": "object",
+			"properties": {
+				"txHash": {"type": "string", "pattern": "^0x[0-9a-fA-F]{64}$"},
+				"from": address,
+				"to": address,
+				"value": {"type": "string", "pattern": "^0x[0-9a-fA-F]{1,64}$"},
+				"contract": {"type": ["string", "null"], "pattern": "^0x[0-9a-fA-F]{40}$"}
+			},
+			"required": ["txHash", "from", "to", "value", "contract"],
+			"additionalProperties": false
+		})
+	}
+}
@@ -0,0 +1,411 @@
+use crate::{
+	erc20::{
+		ContractAddress,
+		TRANSFER_EVENT_TOPIC,
+	},
+	transaction::{
+		group_transfer_logs,
+		reconcile,
+		reconcile_with_discrepancies,
+		transfer_logs,
+		TransactionAndTransferType,
+		TransferDiscrepancy,
+	},
+	transfer::{
+		Direction,
+		LogTransfer,
+		Price,
+		Transfer,
+		TransferSummary,
+		TransferType,
+	},
+	ERC20Error,
+};
+use std::{
+	convert::{
+		TryFrom,
+		TryInto,
+	},
+	str::FromStr,
+};
+use web3::types::{
+	Bytes,
+	H160,
+	H256,
+	Log,
+	Transaction,
+	U256,
+	U64,
+};
+
+fn transfer_log(from: H160, to: H160, value: U256) -> Log {
+	let mut from_topic = [0u8; 32];
+	from_topic[12..].copy_from_slice(&from.0);
+	let mut to_topic = [0u8; 32];
+	to_topic[12..].copy_from_slice(&to.0);
+	let mut data = [0u8; 32];
+	value.to_big_endian(&mut data);
+
+	Log {
+		address: H160::from_str("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap(),
+		topics: vec![TRANSFER_EVENT_TOPIC, from_topic.into(), to_topic.into()],
+		data: Bytes(data.to_vec()),
+		block_hash: Some(H256::random()),
+		block_number: Some(U64::from(11_000_000)),
+		transaction_hash: Some(H256::from_str("43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee").unwrap()),
+		transaction_index: Some(U64::from(3)),
+		log_index: None,
+		transaction_log_index: None,
+		log_type: None,
+		removed: None,
+	}
+}
+
+/// Transfer with a truncated input, only reachable through deserialization.
+fn malformed_transfer(hash: H256) -> TransactionAndTransferType {
+	let transaction = Transaction {
+		hash,
+		to: Some(H160::from_str("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap()),
+		input: Bytes(hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap()),
+		..Default::default()
+	};
+	let mut serialized = serde_json::to_value(TransactionAndTransferType::try_from(transaction).unwrap()).unwrap();
+	serialized["transaction"]["input"] = serde_json::json!("0xa9059cbb");
+	serde_json::from_value(serialized).unwrap()
+}
+
+#[test]
+fn parse_log_transfer() {
+	let from = H160::random();
+	let to = H160::random();
+	let log = transfer_log(from, to, U256::from(1000));
+
+	let resp: Result<LogTransfer, ERC20Error> = log.clone().try_into();
+	assert!(resp.is_ok());
+	let resp = resp.unwrap();
+	assert_eq!(from, resp.from());
+	assert_eq!(to, resp.to());
+	assert_eq!(U256::from(1000), resp.value());
+	assert_eq!(Some(log.address), resp.contract());
+	assert_eq!(log.transaction_hash.unwrap(), resp.tx_hash());
+	assert_eq!(log.block_hash, resp.block_hash());
+	assert_eq!(log.block_number, resp.block_number());
+	assert_eq!(log.transaction_index, resp.transaction_index());
+}
+
+#[test]
+fn parse_log_not_transfer() {
+	let mut log = transfer_log(H160::random(), H160::random(), U256::from(1000));
+	log.topics[0] = H256::random();
+
+	let resp: Result<LogTransfer, ERC20Error> = log.try_into();
+	assert_eq!(Err(ERC20Error::UnexpectedType), resp);
+}
+
+#[test]
+fn log_and_calldata_transfers() {
+	let serialized_str = "a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000";
+	let transaction = Transaction {
+		hash: H256::random(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from: H160::random(),
+		to: Some(H160::random()),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode(serialized_str).unwrap()),
+		raw: None,
+	};
+	let calldata_transfer: TransactionAndTransferType = transaction.try_into().unwrap();
+	let log_transfer: LogTransfer = transfer_log(H160::random(), H160::random(), U256::from(1)).try_into().unwrap();
+
+	let transfers: Vec<Box<dyn Transfer>> = vec![calldata_transfer.into_boxed(), log_transfer.into_boxed()];
+	assert!(transfers.iter().all(|it| it.is_erc20()));
+}
+
+#[test]
+fn transfer_type_bincode_round_trip() {
+	for transfer_type in vec![TransferType::Ethereum, TransferType::ERC20] {
+		let encoded = bincode::serialize(&transfer_type).unwrap();
+		assert_eq!(transfer_type, bincode::deserialize::<TransferType>(&encoded).unwrap());
+	}
+}
+
+#[test]
+fn transfer_summary_schema() {
+	let summary = TransferSummary {
+		tx_hash: H256::random(),
+		from: H160::random(),
+		to: H160::random(),
+		value: U256::from(1000),
+		contract: None,
+	};
+	let serialized = serde_json::to_value(&summary).unwrap();
+	let schema = TransferSummary::json_schema();
+
+	for (key, value) in serialized.as_object().unwrap() {
+		assert!(schema["properties"].get(key).is_some(), "missing {}", key);
+		assert!(value.is_string() || value.is_null());
+	}
+	assert_eq!("0x3e8", serialized["value"]);
+	assert_eq!(serde_json::Value::Null, serialized["contract"]);
+}
+
+#[test]
+fn parse_log_transfer_layouts() {
+	let from = H160::random();
+	let to = H160::random();
+	let standard = transfer_log(from, to, U256::from(1000));
+
+	let mut all_indexed = standard.clone();
+	all_indexed.topics.push(H256::from_slice(&standard.data.0));
+	all_indexed.data = Bytes(vec![]);
+	let resp: LogTransfer = all_indexed.try_into().unwrap();
+	assert_eq!((from, to, U256::from(1000)), (resp.from(), resp.to(), resp.value()));
+
+	// An ERC-721 `Transfer` cannot be told apart, the `tokenId` is taken as the value.
+	let mut erc721 = transfer_log(from, to, U256::zero());
+	erc721.topics.push(H256::from_low_u64_be(42));
+	erc721.data = Bytes(vec![]);
+	let resp: LogTransfer = erc721.try_into().unwrap();
+	assert_eq!(U256::from(42), resp.value());
+
+	let mut none_indexed = standard.clone();
+	none_indexed.topics = vec![TRANSFER_EVENT_TOPIC];
+	none_indexed.data = Bytes([standard.topics[1].0.to_vec(), standard.topics[2].0.to_vec(), standard.data.0.clone()].concat());
+	let resp: Result<LogTransfer, ERC20Error> = none_indexed.try_into();
+	assert_eq!(Err(ERC20Error::UnexpectedSize), resp);
+
+	let mut unexpected = standard;
+	unexpected.topics.truncate(2);
+	let resp: Result<LogTransfer, ERC20Error> = unexpected.try_into();
+	assert_eq!(Err(ERC20Error::UnexpectedSize), resp);
+}
+
+#[test]
+fn transfer_id() {
+	let mut log = transfer_log(H160::random(), H160::random(), U256::from(1000));
+	let transfer: LogTransfer = log.clone().try_into().unwrap();
+	assert_eq!("0x43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee:3", transfer.id());
+
+	log.transaction_index = None;
+	let transfer: LogTransfer = log.try_into().unwrap();
+	assert_eq!("0x43a5d6d13b6a9dca381e3f4b4677a4b9e5d9f80d1a5b6cfa2b1404fab733bcee:pending", transfer.id());
+}
+
+#[test]
+fn parse_log_transfer_data_length() {
+	let log = transfer_log(H160::random(), H160::random(), U256::from(1000));
+	let resp: LogTransfer = log.clone().try_into().unwrap();
+	assert_eq!(U256::from(1000), resp.value());
+
+	let mut longer = log.clone();
+	longer.data.0.extend_from_slice(&[0xff; 8]);
+	let resp: LogTransfer = longer.try_into().unwrap();
+	assert_eq!(U256::from(1000), resp.value());
+
+	let mut shorter = log;
+	shorter.data.0.truncate(31);
+	let resp: Result<LogTransfer, ERC20Error> = shorter.try_into();
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), resp);
+}
+
+#[test]
+fn reconcile_calldata_and_logs() {
+	let from = H160::random();
+	let to = H160::from_str("6748f50f686bfbca6fe8ad62b22228b87f31ff2b").unwrap();
+	let log = transfer_log(from, to, U256::from_dec_str("1000000000000000000000").unwrap());
+	let transaction = Transaction {
+		hash: log.transaction_hash.unwrap(),
+		nonce: Default::default(),
+		block_hash: None,
+		block_number: None,
+		transaction_index: None,
+		from,
+		to: Some(log.address),
+		value: Default::default(),
+		gas_price: Default::default(),
+		gas: Default::default(),
+		input: Bytes(hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap()),
+		raw: None,
+	};
+	let duplicated: TransactionAndTransferType = transaction.try_into().unwrap();
+	let ether = TransactionAndTransferType::ethereum(from, to, U256::from(1), H256::random());
+	let log_transfer: LogTransfer = log.try_into().unwrap();
+
+	let malformed = malformed_transfer(H256::random());
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), malformed.dedup_key());
+
+	let resp = reconcile(vec![duplicated, malformed, ether.clone()], vec![log_transfer.clone()]);
+	assert_eq!(2, resp.len());
+	assert_eq!(log_transfer.dedup_key(), resp[0].dedup_key());
+	assert_eq!(log_transfer.block_number(), resp[0].block_number());
+	assert_eq!(ether.dedup_key(), resp[1].dedup_key());
+}
+
+#[test]
+fn mint_and_burn_logs() {
+	let mint: LogTransfer = transfer_log(H160::zero(), H160::random(), U256::from(1)).try_into().unwrap();
+	assert!(mint.is_mint());
+	assert!(!mint.is_burn());
+
+	let burn: LogTransfer = transfer_log(H160::random(), H160::zero(), U256::from(1)).try_into().unwrap();
+	assert!(!burn.is_mint());
+	assert!(burn.is_burn());
+
+	// Sent from the zero address, but it cannot be decoded.
+	let malformed = malformed_transfer(H256::random());
+	assert!(!malformed.is_mint());
+	assert!(!malformed.is_burn());
+}
+
+#[test]
+fn transfer_direction() {
+	let (wallet, other) = (H160::random(), H160::random());
+	let direction = |from: H160, to: H160| {
+		let transfer: LogTransfer = transfer_log(from, to, U256::from(1)).try_into().unwrap();
+		transfer.direction(wallet)
+	};
+
+	assert_eq!(Direction::Incoming, direction(other, wallet));
+	assert_eq!(Direction::Outgoing, direction(wallet, other));
+	assert_eq!(Direction::SelfTransfer, direction(wallet, wallet));
+	assert_eq!(Direction::Unrelated, direction(other, H160::random()));
+
+	// The malformed transfer is sent from the zero address, but it cannot be decoded.
+	let malformed = malformed_transfer(H256::random());
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), malformed.try_from_addr());
+	assert_eq!(Direction::Unrelated, malformed.direction(H160::zero()));
+}
+
+#[test]
+fn value_decimal_string() {
+	let value = U256::from_dec_str("123456789000000000000").unwrap();
+	let transfer: LogTransfer = transfer_log(H160::random(), H160::random(), value).try_into().unwrap();
+
+	assert_eq!("123456789000000000000", transfer.value_decimal_string());
+}
+
+#[test]
+fn to_known_contract() {
+	let usdt = H160::from_str("dac17f958d2ee523a2206206994597c13d831ec7").unwrap();
+	let transfer: LogTransfer = transfer_log(H160::random(), usdt, U256::from(1)).try_into().unwrap();
+	assert!(transfer.to_is_in_known_contract_set());
+
+	let transfer: LogTransfer = transfer_log(usdt, H160::random(), U256::from(1)).try_into().unwrap();
+	assert!(!transfer.to_is_in_known_contract_set());
+}
+
+#[test]
+fn stream_transfer_logs() {
+	let mut other = transfer_log(H160::random(), H160::random(), U256::from(1));
+	other.topics[0] = H256::random();
+	let logs = vec![
+		transfer_log(H160::random(), H160::random(), U256::from(1)),
+		other,
+		transfer_log(H160::random(), H160::random(), U256::from(2)),
+	];
+
+	let resp: Vec<Result<U256, ERC20Error>> = transfer_logs(logs.into_iter())
+		.map(|it| it.map(|transfer| transfer.value()))
+		.collect();
+	assert_eq!(vec![Ok(U256::from(1)), Err(ERC20Error::UnexpectedType), Ok(U256::from(2))], resp);
+}
+
+#[test]
+fn reconcile_fee_on_transfer_discrepancy() {
+	let from = H160::random();
+	let to = H160::from_str("6748f50f686bfbca6fe8ad62b22228b87f31ff2b").unwrap();
+	let log = transfer_log(from, to, U256::from_dec_str("990000000000000000000").unwrap());
+	let transaction = Transaction {
+		hash: log.transaction_hash.unwrap(),
+		from,
+		to: Some(log.address),
+		input: Bytes(hex::decode("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap()),
+		..Default::default()
+	};
+	let calldata = TransactionAndTransferType::try_from(transaction).unwrap();
+	let log_transfer: LogTransfer = log.clone().try_into().unwrap();
+
+	let malformed = malformed_transfer(H256::from_low_u64_be(1));
+
+	let (transfers, discrepancies) = reconcile_with_discrepancies(vec![calldata, malformed], vec![log_transfer]);
+	assert_eq!(2, transfers.len());
+	assert_eq!(vec![
+		TransferDiscrepancy::Mismatch {
+			tx_hash: log.transaction_hash.unwrap(),
+			calldata_contract: Some(log.address),
+			log_contract: log.address,
+			calldata_value: U256::from_dec_str("1000000000000000000000").unwrap(),
+			log_value: U256::from_dec_str("990000000000000000000").unwrap(),
+		},
+		TransferDiscrepancy::Undecodable {
+			tx_hash: H256::from_low_u64_be(1),
+			error: ERC20Error::UnexpectedEndOfData,
+		},
+	], discrepancies);
+}
+
+#[test]
+fn group_logs_by_transaction() {
+	let other_hash = H256::random();
+	let mut other_tx = transfer_log(H160::random(), H160::random(), U256::from(3));
+	other_tx.transaction_hash = Some(other_hash);
+	let mut approval = transfer_log(H160::random(), H160::random(), U256::from(4));
+	approval.topics[0] = H256::random();
+	let first = transfer_log(H160::random(), H160::random(), U256::from(1));
+	let tx_hash = first.transaction_hash.unwrap();
+	let logs = vec![first, other_tx, approval, transfer_log(H160::random(), H160::random(), U256::from(2))];
+
+	let resp = group_transfer_logs(logs);
+	assert_eq!(2, resp.len());
+	let values: Vec<U256> = resp[&tx_hash].iter().map(|it| it.value()).collect();
+	assert_eq!(vec![U256::from(1), U256::from(2)], values);
+	assert_eq!(1, resp[&other_hash].len());
+}
+
+struct UsdcOnly;
+
+impl Price for UsdcOnly {
+	fn price_usd(&self, token: &ContractAddress) -> Option<f64> {
+		match token {
+			ContractAddress::USDC => Some(0.5),
+			_ => None,
+		}
+	}
+}
+
+#[test]
+fn value_in_usd() {
+	let transfer: LogTransfer = transfer_log(H160::random(), H160::random(), U256::from(3_000_000)).try_into().unwrap();
+	let value = transfer.value_usd(&UsdcOnly, 6).unwrap();
+	assert!((value - 1.5).abs() < 1e-9);
+
+	let ether = TransactionAndTransferType::ethereum(H160::random(), H160::random(), U256::from(1), H256::random());
+	assert_eq!(None, ether.value_usd(&UsdcOnly, 18));
+}
+
+#[test]
+fn removed_log_transfer() {
+	let mut log = transfer_log(H160::random(), H160::random(), U256::from(1));
+	let transfer: LogTransfer = log.clone().try_into().unwrap();
+	assert!(!transfer.is_removed());
+
+	log.removed = Some(true);
+	let resp: Vec<bool> = transfer_logs(vec![log].into_iter())
+		.map(|it| it.unwrap().is_removed())
+		.collect();
+	assert_eq!(vec![true], resp);
+}
+
+#[test]
+fn contract_as_token() {
+	let transfer: LogTransfer = transfer_log(H160::random(), H160::random(), U256::from(1)).try_into().unwrap();
+	assert_eq!(Some(ContractAddress::USDC), transfer.contract_token());
+
+	let ether = TransactionAndTransferType::ethereum(H160::random(), H160::random(), U256::from(1), H256::random());
+	assert_eq!(None, ether.contract_token());
+}
@@ -1,6 +1,14 @@
 ///! A set of useful methods and abstractions.
 
-use crate::ERC20Error;
+use crate::{
+	erc20::ERC20Method,
+	ERC20Error,
+};
+use std::convert::TryInto;
+use tiny_keccak::{
+	Hasher,
+	Keccak,
+};
 use web3::types::{
 	Bytes,
 	H160,
@@ -8,8 +16,40 @@ use web3::types::{
 	U256,
 };
 
-const WORD_SIZE_256_BITS: usize = 32;
-const WORD_SIZE_160_BITS: usize = 20;
+/// Size in bytes of an ABI word, used by numbers, addresses, offsets, and lengths.
+pub const WORD_SIZE: usize = 32;
+/// Size in bytes of an address, left padded to `WORD_SIZE` when ABI encoded.
+pub const ADDRESS_SIZE: usize = 20;
+
+/// ABI type of a value decoded by `BytesToFixedNumber::next_typed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AbiType {
+	/// `address`
+	Address,
+	/// `uint256`
+	Uint256,
+	/// `bool`
+	Bool,
+	/// `bytes32`
+	Bytes32,
+	/// Dynamic `bytes`
+	DynamicBytes,
+}
+
+/// Value decoded by `BytesToFixedNumber::next_typed`, tagged with its ABI type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AbiValue {
+	/// `address`
+	Address(H160),
+	/// `uint256`
+	Uint256(U256),
+	/// `bool`
+	Bool(bool),
+	/// `bytes32`
+	Bytes32(H256),
+	/// Dynamic `bytes`
+	DynamicBytes(Vec<u8>),
+}
 
 /// Converts `Bytes` and `Vec<u8>` to H160, H256, and U256.
 pub struct BytesToFixedNumber {
@@ -69,33 +109,152 @@ impl BytesToFixedNumber {
 
 	/// Returns the next H160.
 	pub fn next_h160(&mut self) -> Result<H160, ERC20Error> {
-		self.skip(WORD_SIZE_256_BITS - WORD_SIZE_160_BITS)?;
+		self.skip(WORD_SIZE - ADDRESS_SIZE)?;
 		self.next_h160_not_padded()
 	}
 
 	/// Returns the next H160 with no padding to 32 bytes.
 	pub fn next_h160_not_padded(&mut self) -> Result<H160, ERC20Error> {
-		let vec_resp = self.next_vec(WORD_SIZE_160_BITS)?;
-		let mut the_vec: [u8; WORD_SIZE_160_BITS] = [0; WORD_SIZE_160_BITS];
-		the_vec[..WORD_SIZE_160_BITS].clone_from_slice(&vec_resp[..WORD_SIZE_160_BITS]);
+		let vec_resp = self.next_vec(ADDRESS_SIZE)?;
+		let mut the_vec: [u8; ADDRESS_SIZE] = [0; ADDRESS_SIZE];
+		the_vec[..ADDRESS_SIZE].clone_from_slice(&vec_resp[..ADDRESS_SIZE]);
 		Ok(the_vec.into())
 	}
 
 	/// Returns the next H256.
 	pub fn next_h256(&mut self) -> Result<H256, ERC20Error> {
-		let vec_resp = self.next_vec(WORD_SIZE_256_BITS)?;
-		let mut the_vec: [u8; WORD_SIZE_256_BITS] = [0; WORD_SIZE_256_BITS];
-		the_vec[..WORD_SIZE_256_BITS].clone_from_slice(&vec_resp[..WORD_SIZE_256_BITS]);
+		let vec_resp = self.next_vec(WORD_SIZE)?;
+		let mut the_vec: [u8; WORD_SIZE] = [0; WORD_SIZE];
+		the_vec[..WORD_SIZE].clone_from_slice(&vec_resp[..WORD_SIZE]);
 		Ok(the_vec.into())
 	}
 
 	/// Returns the next U256.
 	pub fn next_u256(&mut self) -> Result<U256, ERC20Error> {
-		let vec_resp = self.next_vec(WORD_SIZE_256_BITS)?;
-		let mut the_vec: [u8; WORD_SIZE_256_BITS] = [0; WORD_SIZE_256_BITS];
-		the_vec[..WORD_SIZE_256_BITS].clone_from_slice(&vec_resp[..WORD_SIZE_256_BITS]);
+		let vec_resp = self.next_vec(WORD_SIZE)?;
+		let mut the_vec: [u8; WORD_SIZE] = [0; WORD_SIZE];
+		the_vec[..WORD_SIZE].clone_from_slice(&vec_resp[..WORD_SIZE]);
 		Ok(the_vec.into())
 	}
+
+	/// Returns the next U256 encoded with `size` bytes, right aligned.
+	///
+	/// Strict ABI encoding always uses 32 bytes, see `next_u256`.
+	/// This is meant only to recover data from non-compliant contracts.
+	///
+	/// # Arguments
+	///
+	/// * `size` - The number of bytes used by the value, up to 32.
+	///
+	pub fn next_u256_lenient(&mut self, size: usize) -> Result<U256, ERC20Error> {
+		if size > WORD_SIZE {
+			return Err(ERC20Error::UnexpectedSize);
+		}
+		let vec_resp = self.next_vec(size)?;
+		Ok(U256::from_big_endian(&vec_resp))
+	}
+
+	/// Returns the next word as an `usize`, used for offsets and lengths of dynamic types.
+	pub fn next_usize(&mut self) -> Result<usize, ERC20Error> {
+		let value = self.next_u256()?;
+		if value > U256::from(self.data.len()) {
+			return Err(ERC20Error::UnexpectedSize);
+		}
+		Ok(value.as_usize())
+	}
+
+	/// Runs `read` with the position moved to `offset`, restoring the current position afterwards.
+	///
+	/// The dynamic types are decoded in place, with no copies of the data after the offset.
+	fn read_at<T>(
+		&mut self, offset: usize, read: impl FnOnce(&mut Self) -> Result<T, ERC20Error>,
+	) -> Result<T, ERC20Error> {
+		if offset > self.data.len() {
+			return Err(ERC20Error::UnexpectedEndOfData);
+		}
+		let index = std::mem::replace(&mut self.index, offset);
+		let resp = read(self);
+		self.index = index;
+		resp
+	}
+
+	/// Returns the length prefixed content of a dynamic `bytes` at the current position.
+	fn next_bytes_content(&mut self) -> Result<Vec<u8>, ERC20Error> {
+		let size = self.next_usize()?;
+		self.next_vec(size)
+	}
+
+	/// Returns the next dynamic `bytes`, reading its offset from the current position.
+	pub fn next_bytes(&mut self) -> Result<Vec<u8>, ERC20Error> {
+		let offset = self.next_usize()?;
+		self.read_at(offset, Self::next_bytes_content)
+	}
+
+	/// Returns the next dynamic `bytes[]`, reading its offset from the current position.
+	pub fn next_bytes_array(&mut self) -> Result<Vec<Vec<u8>>, ERC20Error> {
+		let offset = self.next_usize()?;
+		self.read_at(offset, |it| {
+			let size = it.next_usize()?;
+			// The offsets of the elements are relative to the beginning of the array content.
+			let content = it.index;
+			let mut resp = Vec::new();
+			for _ in 0..size {
+				let element = it.next_usize()?;
+				resp.push(it.read_at(content + element, Self::next_bytes_content)?);
+			}
+			Ok(resp)
+		})
+	}
+
+	/// Returns the next dynamic `address[]`, reading its offset from the current position.
+	pub fn next_h160_array(&mut self) -> Result<Vec<H160>, ERC20Error> {
+		let offset = self.next_usize()?;
+		self.read_at(offset, |it| {
+			let size = it.next_usize()?;
+			let mut resp = Vec::new();
+			for _ in 0..size {
+				resp.push(it.next_h160()?);
+			}
+			Ok(resp)
+		})
+	}
+
+	/// Returns the next dynamic `uint256[]`, reading its offset from the current position.
+	pub fn next_u256_array(&mut self) -> Result<Vec<U256>, ERC20Error> {
+		let offset = self.next_usize()?;
+		self.read_at(offset, |it| {
+			let size = it.next_usize()?;
+			let mut resp = Vec::new();
+			for _ in 0..size {
+				resp.push(it.next_u256()?);
+			}
+			Ok(resp)
+		})
+	}
+
+	/// Returns the next value of the ABI type.
+	///
+	/// A `bool` other than zero or one is rejected with `UnexpectedType`.
+	///
+	/// # Arguments
+	///
+	/// * `ty` - The ABI type of the value.
+	///
+	pub fn next_typed(&mut self, ty: AbiType) -> Result<AbiValue, ERC20Error> {
+		Ok(match ty {
+			AbiType::Address => AbiValue::Address(self.next_h160()?),
+			AbiType::Uint256 => AbiValue::Uint256(self.next_u256()?),
+			AbiType::Bool => {
+				let value = self.next_u256()?;
+				if value > U256::one() {
+					return Err(ERC20Error::UnexpectedType);
+				}
+				AbiValue::Bool(value == U256::one())
+			}
+			AbiType::Bytes32 => AbiValue::Bytes32(self.next_h256()?),
+			AbiType::DynamicBytes => AbiValue::DynamicBytes(self.next_bytes()?),
+		})
+	}
 }
 
 /// Converts H160, H256, and U256 into `Vec<u8>` which can be used to create a `Bytes`.
@@ -111,10 +270,22 @@ impl FixedNumberToBytes {
 	/// * `vec` - Vector with the bytes to be added.
 	///
 	// pub fn push_vec(&mut self, vec: &Vec<u8>) {
-	pub fn push_vec(&mut self, vec: &[u8]) {
+	pub fn push_vec(&mut self, vec: &[u8]) -> &mut Self {
 		for it in vec {
 			self.data.push(*it);
 		}
+		self
+	}
+
+	/// Pushes the selector of an identified method to the tail of the current byte array.
+	///
+	/// # Arguments
+	///
+	/// * `method` - Method with the selector to be pushed.
+	///
+	pub fn push_selector(&mut self, method: ERC20Method) -> Result<&mut Self, ERC20Error> {
+		let selector: [u8; 4] = method.try_into()?;
+		Ok(self.push_vec(&selector))
 	}
 
 	/// Pushes a H160 to the tail of the current byte array.
@@ -123,12 +294,9 @@ impl FixedNumberToBytes {
 	///
 	/// * `value` - H160 to be pushed.
 	///
-	#[allow(clippy::same_item_push)]
-	pub fn push_h160(&mut self, value: &H160) {
-		for _ in 0..(WORD_SIZE_256_BITS - WORD_SIZE_160_BITS) {
-			self.data.push(0);
-		}
-		self.push_h160_not_padded(value);
+	pub fn push_h160(&mut self, value: &H160) -> &mut Self {
+		// An address always fits in a word.
+		self.push_vec(&left_pad_32(&value.0).unwrap())
 	}
 
 	/// Pushes a H160 to the tail of the current byte array, with no padding to 32 bytes.
@@ -137,10 +305,8 @@ impl FixedNumberToBytes {
 	///
 	/// * `value` - H160 to be pushed.
 	///
-	pub fn push_h160_not_padded(&mut self, value: &H160) {
-		for it in value.0.iter() {
-			self.data.push(*it);
-		}
+	pub fn push_h160_not_padded(&mut self, value: &H160) -> &mut Self {
+		self.push_vec(&value.0)
 	}
 
 	/// Pushes a H256 to the tail of the current byte array.
@@ -149,10 +315,8 @@ impl FixedNumberToBytes {
 	///
 	/// * `value` - H256 to be pushed.
 	///
-	pub fn push_h256(&mut self, value: &H256) {
-		for it in value.0.iter() {
-			self.data.push(*it);
-		}
+	pub fn push_h256(&mut self, value: &H256) -> &mut Self {
+		self.push_vec(&value.0)
 	}
 
 	/// Pushes an U256 to the tail of the current byte array.
@@ -161,10 +325,16 @@ impl FixedNumberToBytes {
 	///
 	/// * `value` - U256 to be pushed.
 	///
-	pub fn push_u256(&mut self, value: &U256) {
-		for i in (0..WORD_SIZE_256_BITS).rev() {
+	pub fn push_u256(&mut self, value: &U256) -> &mut Self {
+		for i in (0..WORD_SIZE).rev() {
 			self.data.push(value.byte(i));
 		}
+		self
+	}
+
+	/// Returns a copy of the bytes pushed so far, useful at the end of a chain of `push_*` calls.
+	pub fn to_vec(&self) -> Vec<u8> {
+		self.data.clone()
 	}
 }
 
@@ -181,3 +351,297 @@ impl From<FixedNumberToBytes> for Vec<u8> {
 		data.data
 	}
 }
+
+/// Decodes a hex string, with or without the `0x` prefix, into bytes.
+///
+/// # Arguments
+///
+/// * `s` - Hex string to be decoded.
+///
+pub fn decode_hex(s: &str) -> Result<Vec<u8>, ERC20Error> {
+	let s = s.strip_prefix("0x").unwrap_or(s);
+	hex::decode(s).map_err(|_| ERC20Error::UnexpectedType)
+}
+
+/// Encodes bytes as a lowercase hex string prefixed with `0x`.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes to be encoded.
+///
+pub fn encode_hex(bytes: &[u8]) -> String {
+	format!("0x{}", encode_hex_no_prefix(bytes))
+}
+
+/// Encodes bytes as a lowercase hex string with no prefix.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes to be encoded.
+///
+pub fn encode_hex_no_prefix(bytes: &[u8]) -> String {
+	hex::encode(bytes)
+}
+
+/// Pads the bytes with zeros to the left into a 32 bytes word, as used for numbers and addresses.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes to be padded, up to 32.
+///
+pub fn left_pad_32(bytes: &[u8]) -> Result<[u8; 32], ERC20Error> {
+	if bytes.len() > WORD_SIZE {
+		return Err(ERC20Error::UnexpectedSize);
+	}
+	let mut resp = [0; WORD_SIZE];
+	resp[WORD_SIZE - bytes.len()..].clone_from_slice(bytes);
+	Ok(resp)
+}
+
+/// Pads the bytes with zeros to the right into a 32 bytes word, as used for `bytes` and `bytesN`.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes to be padded, up to 32.
+///
+pub fn right_pad_32(bytes: &[u8]) -> Result<[u8; 32], ERC20Error> {
+	if bytes.len() > WORD_SIZE {
+		return Err(ERC20Error::UnexpectedSize);
+	}
+	let mut resp = [0; WORD_SIZE];
+	resp[..bytes.len()].clone_from_slice(bytes);
+	Ok(resp)
+}
+
+/// Converts the value in base units into an approximate float dividing it by `10^decimals`.
+///
+/// The conversion is lossy: `f64` keeps only 53 bits of precision, so large values are rounded.
+/// Use it only when an approximation is acceptable, like for dashboards.
+///
+/// # Arguments
+///
+/// * `value` - Value in base units.
+/// * `decimals` - Number of decimals for the token.
+///
+pub fn u256_to_f64(value: U256, decimals: u8) -> f64 {
+	let base_units = value.0.iter().rev()
+		.fold(0.0, |acc, limb| acc * 18_446_744_073_709_551_616.0 + *limb as f64);
+	base_units / 10f64.powi(i32::from(decimals))
+}
+
+/// Parses a decimal amount into base units, multiplying it by `10^decimals`, e.g. `"1.5"` with 6 decimals is 1500000.
+///
+/// It accepts an optional scientific notation exponent, like `"1.5e3"`, applied before the decimals scaling.
+/// Returns `UnexpectedType` for invalid numbers or amounts with fractional base units, and `Overflow` if it does not fit.
+///
+/// # Arguments
+///
+/// * `s` - Decimal amount, like `"0.000001"` or `"1e18"`.
+/// * `decimals` - Number of decimals for the token.
+///
+pub fn parse_units(s: &str, decimals: u8) -> Result<U256, ERC20Error> {
+	let (mantissa, exponent) = match s.find(|it| it == 'e' || it == 'E') {
+		Some(i) => (&s[..i], s[i + 1..].parse::<i64>().map_err(|_| ERC20Error::UnexpectedType)?),
+		None => (s, 0),
+	};
+	let (integer, fraction) = match mantissa.find('.') {
+		Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+		None => (mantissa, ""),
+	};
+	let is_digits = |it: &str| it.chars().all(|c| c.is_ascii_digit());
+	if (integer.is_empty() && fraction.is_empty()) || !is_digits(integer) || !is_digits(fraction) {
+		return Err(ERC20Error::UnexpectedType);
+	}
+	let mut digits = format!("{}{}", integer, fraction);
+	let mut shift = exponent
+		.checked_add(i64::from(decimals) - fraction.len() as i64)
+		.ok_or(ERC20Error::Overflow)?;
+	// The digits past the base unit must be zeros.
+	while shift < 0 {
+		match digits.pop() {
+			Some('0') => shift += 1,
+			Some(_) => return Err(ERC20Error::UnexpectedType),
+			None => return Ok(U256::zero()),
+		}
+	}
+	let value = if digits.is_empty() {
+		U256::zero()
+	} else {
+		U256::from_dec_str(&digits).map_err(|_| ERC20Error::Overflow)?
+	};
+	if value.is_zero() {
+		return Ok(value);
+	}
+	U256::from(10)
+		.checked_pow(U256::from(shift))
+		.and_then(|scale| value.checked_mul(scale))
+		.ok_or(ERC20Error::Overflow)
+}
+
+/// Returns the canonical key for the address, the 40 chars lowercase hex with no `0x` prefix.
+///
+/// # Arguments
+///
+/// * `addr` - Address to be converted.
+///
+pub fn h160_to_key(addr: &H160) -> String {
+	encode_hex_no_prefix(&addr.0)
+}
+
+/// Parses an address from its canonical key, see `h160_to_key`.
+///
+/// # Arguments
+///
+/// * `key` - The 40 chars hex key.
+///
+pub fn key_to_h160(key: &str) -> Result<H160, ERC20Error> {
+	let bytes = decode_hex(key)?;
+	if bytes.len() != ADDRESS_SIZE {
+		return Err(ERC20Error::UnexpectedSize);
+	}
+	Ok(H160::from_slice(&bytes))
+}
+
+/// Creates an H160 from its bytes.
+pub fn h160_from_bytes(b: [u8; 20]) -> H160 {
+	b.into()
+}
+
+/// Creates an H256 from its bytes.
+pub fn h256_from_bytes(b: [u8; 32]) -> H256 {
+	b.into()
+}
+
+/// Creates an U256 from its big endian bytes.
+pub fn u256_from_be_bytes(b: [u8; 32]) -> U256 {
+	b.into()
+}
+
+/// Converts a 32 bytes word, like an indexed event topic, into an U256.
+pub fn h256_to_u256(h: H256) -> U256 {
+	U256::from_big_endian(&h.0)
+}
+
+/// Splits the calldata into the method selector and the argument words.
+///
+/// # Arguments
+///
+/// * `input` - The transaction input.
+///
+pub fn split_calldata(input: &[u8]) -> Result<([u8; 4], Vec<[u8; WORD_SIZE]>), ERC20Error> {
+	if input.len() < 4 {
+		return Err(ERC20Error::UnexpectedEndOfData);
+	}
+	if (input.len() - 4) % WORD_SIZE != 0 {
+		return Err(ERC20Error::UnexpectedSize);
+	}
+	let mut selector = [0; 4];
+	selector.clone_from_slice(&input[..4]);
+	let words = input[4..].chunks(WORD_SIZE).map(|chunk| {
+		let mut word = [0; WORD_SIZE];
+		word.clone_from_slice(chunk);
+		word
+	}).collect();
+	Ok((selector, words))
+}
+
+/// Subtracts `b` from `a`, returning `Underflow` when `b` is greater than `a`.
+pub fn checked_sub(a: U256, b: U256) -> Result<U256, ERC20Error> {
+	a.checked_sub(b).ok_or(ERC20Error::Underflow)
+}
+
+/// Subtracts `b` from `a`, returning zero when `b` is greater than `a`.
+pub fn saturating_sub(a: U256, b: U256) -> U256 {
+	a.saturating_sub(b)
+}
+
+/// Replaces the method selector of the input, requiring the `test-util` feature.
+///
+/// Useful to check that an otherwise valid calldata is rejected, an input shorter than 4 bytes is replaced entirely.
+///
+/// # Arguments
+///
+/// * `input` - The transaction input.
+/// * `selector` - The new method selector.
+///
+#[cfg(feature = "test-util")]
+pub fn with_selector(input: &[u8], selector: [u8; 4]) -> Vec<u8> {
+	let mut resp = selector.to_vec();
+	if input.len() > 4 {
+		resp.extend_from_slice(&input[4..]);
+	}
+	resp
+}
+
+/// Returns the Keccak-256 hash of the bytes, as used by Ethereum.
+///
+/// # Arguments
+///
+/// * `bytes` - Bytes to be hashed.
+///
+pub fn keccak256(bytes: &[u8]) -> H256 {
+	let mut hasher = Keccak::v256();
+	hasher.update(bytes);
+	let mut resp = [0u8; WORD_SIZE];
+	hasher.finalize(&mut resp);
+	resp.into()
+}
+
+/// Parses an address supplied by a user, validating the EIP-55 checksum when it has mixed case.
+///
+/// All lowercase and all uppercase addresses carry no checksum, so they are accepted as they are.
+/// Returns `UnexpectedType` for an invalid checksum or hex and `UnexpectedSize` if it is not 40 hex characters after
+/// the optional prefix.
+///
+/// # Arguments
+///
+/// * `s` - Hex address, with or without the `0x` prefix.
+///
+pub fn parse_checked_address(s: &str) -> Result<H160, ERC20Error> {
+	let s = s.strip_prefix("0x").unwrap_or(s);
+	if s.len() != 2 * ADDRESS_SIZE {
+		return Err(ERC20Error::UnexpectedSize);
+	}
+	let bytes = hex::decode(s).map_err(|_| ERC20Error::UnexpectedType)?;
+	let has_lowercase = s.chars().any(|it| it.is_ascii_lowercase());
+	let has_uppercase = s.chars().any(|it| it.is_ascii_uppercase());
+	if has_lowercase && has_uppercase {
+		let hash = keccak256(s.to_ascii_lowercase().as_bytes());
+		for (i, c) in s.chars().enumerate() {
+			let nibble = (hash.0[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+			if c.is_ascii_alphabetic() && c.is_ascii_uppercase() != (nibble >= 8) {
+				return Err(ERC20Error::UnexpectedType);
+			}
+		}
+	}
+	Ok(H160::from_slice(&bytes))
+}
+
+/// Splits the inclusive block range into inclusive sub-ranges of at most `chunk` blocks, e.g. to fetch logs.
+///
+/// A `chunk` of zero returns the whole range as a single chunk, and `from` greater than `to` returns no range.
+///
+/// # Arguments
+///
+/// * `from` - First block of the range.
+/// * `to` - Last block of the range.
+/// * `chunk` - Maximum number of blocks of each sub-range.
+///
+pub fn block_ranges(from: u64, to: u64, chunk: u64) -> Vec<(u64, u64)> {
+	if from > to {
+		return Vec::new();
+	}
+	if chunk == 0 {
+		return vec![(from, to)];
+	}
+	let mut resp = Vec::new();
+	let mut start = from;
+	loop {
+		let end = start.saturating_add(chunk - 1).min(to);
+		resp.push((start, end));
+		if end == to {
+			return resp;
+		}
+		start = end + 1;
+	}
+}
@@ -1,9 +1,39 @@
-use crate::util::{
-	BytesToFixedNumber,
-	FixedNumberToBytes,
+use crate::{
+	erc20::ERC20Method,
+	util::{
+		block_ranges,
+		checked_sub,
+		decode_hex,
+		encode_hex,
+		encode_hex_no_prefix,
+		h160_from_bytes,
+		h160_to_key,
+		h256_from_bytes,
+		h256_to_u256,
+		keccak256,
+		key_to_h160,
+		left_pad_32,
+		parse_checked_address,
+		parse_units,
+		right_pad_32,
+		saturating_sub,
+		split_calldata,
+		u256_from_be_bytes,
+		u256_to_f64,
+		AbiType,
+		AbiValue,
+		BytesToFixedNumber,
+		FixedNumberToBytes,
+		ADDRESS_SIZE,
+		WORD_SIZE,
+	},
+	ERC20Error,
 };
+use proptest::prelude::*;
+use std::str::FromStr;
 use web3::types::{
 	H160,
+	H256,
 	U256,
 };
 
@@ -48,3 +78,282 @@ fn fixed_number_to_bytes() {
 
 	assert_eq!(bytes_vec, encoded_vec);
 }
+
+#[test]
+fn transfer_offsets_with_word_size() {
+	let bytes_vec = hex::decode("a9059cbb00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000002").unwrap();
+	assert_eq!(4 + 2 * WORD_SIZE, bytes_vec.len());
+	let address_offset = 4 + WORD_SIZE - ADDRESS_SIZE;
+	assert_eq!(H160::from_low_u64_be(1), H160::from_slice(&bytes_vec[address_offset..address_offset + ADDRESS_SIZE]));
+}
+
+#[test]
+fn split_transfer_calldata() {
+	let bytes_vec = hex::decode("a9059cbb00000000000000000000000000000000000000000000000000000000000000010000000000000000000000000000000000000000000000000000000000000002").unwrap();
+	let (selector, words) = split_calldata(&bytes_vec).unwrap();
+	assert_eq!([0xa9, 0x05, 0x9c, 0xbb], selector);
+	assert_eq!(2, words.len());
+	assert_eq!(H160::from_low_u64_be(1).0, words[0][12..]);
+	assert_eq!(U256::from(2), U256::from(words[1]));
+
+	assert!(split_calldata(&bytes_vec[..4]).unwrap().1.is_empty());
+	assert_eq!(Err(ERC20Error::UnexpectedSize), split_calldata(&bytes_vec[..67]));
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), split_calldata(&bytes_vec[..3]));
+}
+
+#[test]
+fn lenient_u256() {
+	let mut decoder: BytesToFixedNumber = vec![0x01, 0x00, 0x02, 0x03].into();
+	assert_eq!(Ok(U256::from(0x0100)), decoder.next_u256_lenient(2));
+	assert_eq!(Ok(U256::from(2)), decoder.next_u256_lenient(1));
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), decoder.next_u256_lenient(2));
+	assert_eq!(Err(ERC20Error::UnexpectedSize), decoder.next_u256_lenient(33));
+}
+
+#[test]
+fn decoding_hex() {
+	assert_eq!(Ok(vec![0xa9, 0x05, 0x9c, 0xbb]), decode_hex("a9059cbb"));
+	assert_eq!(Ok(vec![0xa9, 0x05, 0x9c, 0xbb]), decode_hex("0xa9059cbb"));
+	assert_eq!(Ok(vec![]), decode_hex("0x"));
+	assert_eq!(Err(ERC20Error::UnexpectedType), decode_hex("0xa9059cb"));
+	assert_eq!(Err(ERC20Error::UnexpectedType), decode_hex("zz"));
+}
+
+#[test]
+fn encoding_hex() {
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_vec(&[0xa9, 0x05, 0x9c, 0xbb]);
+	encoder.push_h160(&H160::from_low_u64_be(1));
+	let encoded_vec: Vec<u8> = encoder.into();
+
+	let encoded_str = encode_hex(&encoded_vec);
+	assert_eq!("0xa9059cbb0000000000000000000000000000000000000000000000000000000000000001", encoded_str);
+	assert_eq!(&encoded_str[2..], encode_hex_no_prefix(&encoded_vec));
+	assert_eq!(Ok(encoded_vec), decode_hex(&encoded_str));
+}
+
+#[test]
+fn padding_to_word() {
+	let mut expected = [0; 32];
+	expected[30] = 0xab;
+	expected[31] = 0xcd;
+	assert_eq!(Ok(expected), left_pad_32(&[0xab, 0xcd]));
+
+	let mut expected = [0; 32];
+	expected[0] = 0xab;
+	expected[1] = 0xcd;
+	assert_eq!(Ok(expected), right_pad_32(&[0xab, 0xcd]));
+
+	assert_eq!(Ok([0xff; 32]), left_pad_32(&[0xff; 32]));
+	assert_eq!(Err(ERC20Error::UnexpectedSize), left_pad_32(&[0; 33]));
+	assert_eq!(Err(ERC20Error::UnexpectedSize), right_pad_32(&[0; 33]));
+}
+
+#[test]
+fn u256_to_approximate_f64() {
+	assert!((u256_to_f64(U256::from(1_500_000), 6) - 1.5).abs() < 1e-12);
+	assert!((u256_to_f64(U256::from(1000), 0) - 1000.0).abs() < 1e-12);
+	assert!((u256_to_f64(U256::exp10(18) * U256::from(3), 18) - 3.0).abs() < 1e-12);
+
+	let max = u256_to_f64(U256::max_value(), 18);
+	assert!(max.is_finite());
+	assert!((max / 1.157_920_892_373_162e59 - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn address_key() {
+	let address = H160::from_str("0000000000085d4780B73119b644AE5ecd22b376").unwrap();
+	let key = h160_to_key(&address);
+	assert_eq!("0000000000085d4780b73119b644ae5ecd22b376", key);
+	assert_eq!(Ok(address), key_to_h160(&key));
+	assert_eq!(Err(ERC20Error::UnexpectedSize), key_to_h160("0000000000085d4780b73119b644ae5ecd22b3"));
+}
+
+#[test]
+fn from_byte_arrays() {
+	let mut bytes = [0; 32];
+	bytes[31] = 2;
+	assert_eq!(H256::from_low_u64_be(2), h256_from_bytes(bytes));
+	assert_eq!(U256::from(2), u256_from_be_bytes(bytes));
+
+	let mut bytes = [0; 20];
+	bytes[19] = 1;
+	assert_eq!(H160::from_low_u64_be(1), h160_from_bytes(bytes));
+}
+
+#[test]
+fn subtraction_underflow() {
+	assert_eq!(Ok(U256::from(3)), checked_sub(U256::from(5), U256::from(2)));
+	assert_eq!(Err(ERC20Error::Underflow), checked_sub(U256::from(2), U256::from(5)));
+	assert_eq!(U256::from(3), saturating_sub(U256::from(5), U256::from(2)));
+	assert_eq!(U256::zero(), saturating_sub(U256::from(2), U256::from(5)));
+}
+
+proptest! {
+	#[test]
+	fn transfer_round_trip(address in any::<[u8; 20]>(), value in any::<[u8; 32]>()) {
+		let address: H160 = address.into();
+		let value: U256 = value.into();
+
+		let mut encoder: FixedNumberToBytes = Default::default();
+		encoder.push_vec(&hex::decode("a9059cbb").unwrap());
+		encoder.push_h160(&address);
+		encoder.push_u256(&value);
+		let encoded_vec: Vec<u8> = encoder.into();
+
+		let mut decoder: BytesToFixedNumber = encoded_vec.into();
+		prop_assert_eq!(hex::decode("a9059cbb").unwrap(), decoder.next_vec(4).unwrap());
+		prop_assert_eq!(address, decoder.next_h160().unwrap());
+		prop_assert_eq!(value, decoder.next_u256().unwrap());
+	}
+
+	#[test]
+	fn decoding_arbitrary_bytes_does_not_panic(data in proptest::collection::vec(any::<u8>(), 0..128)) {
+		let mut decoder: BytesToFixedNumber = data.clone().into();
+		let _ignore = decoder.next_h160();
+		let mut decoder: BytesToFixedNumber = data.into();
+		let _ignore = decoder.next_u256();
+	}
+}
+
+#[cfg(feature = "test-util")]
+#[test]
+fn replace_selector() {
+	use crate::util::with_selector;
+
+	let input = decode_hex("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b").unwrap();
+	let corrupted = with_selector(&input, [0xde, 0xad, 0xbe, 0xef]);
+	assert_eq!(&input[4..], &corrupted[4..]);
+	assert_eq!(ERC20Method::UnidentifiedWith([0xde, 0xad, 0xbe, 0xef]), ERC20Method::from(corrupted));
+
+	assert_eq!(vec![0xde, 0xad, 0xbe, 0xef], with_selector(&[0xa9], [0xde, 0xad, 0xbe, 0xef]));
+}
+
+#[test]
+fn typed_decoding() {
+	let address = H160::random();
+	let word = H256::random();
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_h160(&address);
+	encoder.push_u256(&U256::from(1_000));
+	encoder.push_u256(&U256::one());
+	encoder.push_h256(&word);
+	encoder.push_u256(&U256::from(6 * 32));
+	encoder.push_u256(&U256::from(2));
+	encoder.push_u256(&U256::from(2));
+	encoder.push_vec(&right_pad_32(&[0xab, 0xcd]).unwrap());
+	let data: Vec<u8> = encoder.into();
+
+	let mut decoder: BytesToFixedNumber = data.into();
+	assert_eq!(Ok(AbiValue::Address(address)), decoder.next_typed(AbiType::Address));
+	assert_eq!(Ok(AbiValue::Uint256(U256::from(1_000))), decoder.next_typed(AbiType::Uint256));
+	assert_eq!(Ok(AbiValue::Bool(true)), decoder.next_typed(AbiType::Bool));
+	assert_eq!(Ok(AbiValue::Bytes32(word)), decoder.next_typed(AbiType::Bytes32));
+	assert_eq!(Ok(AbiValue::DynamicBytes(vec![0xab, 0xcd])), decoder.next_typed(AbiType::DynamicBytes));
+	assert_eq!(Err(ERC20Error::UnexpectedType), decoder.next_typed(AbiType::Bool));
+}
+
+#[test]
+fn dynamic_bytes_array() {
+	let mut encoder: FixedNumberToBytes = Default::default();
+	encoder.push_u256(&U256::from(2 * WORD_SIZE)).push_u256(&U256::from(7));
+	// Array with two elements, the offsets are relative to the content after the size.
+	encoder.push_u256(&U256::from(2)).push_u256(&U256::from(2 * WORD_SIZE)).push_u256(&U256::from(4 * WORD_SIZE));
+	encoder.push_u256(&U256::from(3)).push_vec(&right_pad_32(&[1, 2, 3]).unwrap());
+	encoder.push_u256(&U256::zero());
+	let data = encoder.to_vec();
+
+	let mut decoder: BytesToFixedNumber = data.clone().into();
+	assert_eq!(Ok(vec![vec![1, 2, 3], vec![]]), decoder.next_bytes_array());
+	assert_eq!(Ok(U256::from(7)), decoder.next_u256());
+
+	let mut truncated: BytesToFixedNumber = data[..data.len() - WORD_SIZE].to_vec().into();
+	assert_eq!(Err(ERC20Error::UnexpectedEndOfData), truncated.next_bytes_array());
+}
+
+#[test]
+fn indexed_topic_to_u256() {
+	let topic = H256::from_str("00000000000000000000000000000000000000000000003635c9adc5dea00000").unwrap();
+	assert_eq!(U256::from_dec_str("1000000000000000000000").unwrap(), h256_to_u256(topic));
+	assert_eq!(U256::max_value(), h256_to_u256(H256::repeat_byte(0xff)));
+}
+
+#[test]
+fn chained_encoding() {
+	let to = H160::from_str("6748f50f686bfbca6fe8ad62b22228b87f31ff2b").unwrap();
+	let value = U256::from_dec_str("1000000000000000000000").unwrap();
+	let data = FixedNumberToBytes::default()
+		.push_selector(ERC20Method::Transfer).unwrap()
+		.push_h160(&to)
+		.push_u256(&value)
+		.to_vec();
+	assert_eq!(
+		decode_hex("a9059cbb0000000000000000000000006748f50f686bfbca6fe8ad62b22228b87f31ff2b00000000000000000000000000000000000000000000003635c9adc5dea00000"),
+		Ok(data),
+	);
+
+	assert_eq!(
+		Err(ERC20Error::UnexpectedType),
+		FixedNumberToBytes::default().push_selector(ERC20Method::Unidentified).map(|it| it.to_vec()),
+	);
+}
+
+#[test]
+fn keccak256_hash() {
+	assert_eq!(
+		H256::from_str("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470").unwrap(),
+		keccak256(&[]),
+	);
+}
+
+#[test]
+fn checksum_address() {
+	let address = H160::from_str("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed").unwrap();
+	assert_eq!(Ok(address), parse_checked_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+	assert_eq!(Ok(address), parse_checked_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"));
+	assert_eq!(Ok(address), parse_checked_address("5AAEB6053F3E94C9B9A09F33669435E7EF1BEAED"));
+	assert_eq!(
+		Ok(H160::from_str("fb6916095ca1df60bb79ce92ce3ea74c37c5d359").unwrap()),
+		parse_checked_address("0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359"),
+	);
+
+	assert_eq!(Err(ERC20Error::UnexpectedType), parse_checked_address("0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+	assert_eq!(Err(ERC20Error::UnexpectedSize), parse_checked_address("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA"));
+	assert_eq!(Err(ERC20Error::UnexpectedSize), parse_checked_address("0x0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"));
+	assert_eq!(Err(ERC20Error::UnexpectedSize), parse_checked_address("0x0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"));
+	assert_eq!(Err(ERC20Error::UnexpectedSize), parse_checked_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed0"));
+	assert_eq!(Err(ERC20Error::UnexpectedSize), parse_checked_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beae"));
+	assert_eq!(Err(ERC20Error::UnexpectedType), parse_checked_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaeg"));
+}
+
+#[test]
+fn split_block_ranges() {
+	assert_eq!(vec![(10, 19), (20, 29), (30, 35)], block_ranges(10, 35, 10));
+	assert_eq!(vec![(7, 7)], block_ranges(7, 7, 10));
+	assert_eq!(vec![(1, 5)], block_ranges(1, 5, 100));
+	assert_eq!(vec![(1, 5)], block_ranges(1, 5, 0));
+	assert_eq!(Vec::<(u64, u64)>::new(), block_ranges(5, 1, 10));
+	assert_eq!(vec![(u64::max_value() - 1, u64::max_value())], block_ranges(u64::max_value() - 1, u64::max_value(), 10));
+}
+
+#[test]
+fn parse_decimal_units() {
+	assert_eq!(Ok(U256::from(1_500_000)), parse_units("1.5", 6));
+	assert_eq!(Ok(U256::one()), parse_units("0.000001", 6));
+	assert_eq!(Ok(U256::from(42)), parse_units("42", 0));
+	assert_eq!(Err(ERC20Error::UnexpectedType), parse_units("0.0000001", 6));
+	assert_eq!(Err(ERC20Error::UnexpectedType), parse_units("1.2.3", 6));
+	assert_eq!(Err(ERC20Error::UnexpectedType), parse_units("-1", 6));
+	assert_eq!(Err(ERC20Error::UnexpectedType), parse_units("", 6));
+}
+
+#[test]
+fn parse_scientific_units() {
+	assert_eq!(Ok(U256::from_dec_str("1000000000000000000").unwrap()), parse_units("1e18", 0));
+	assert_eq!(Ok(U256::from(1_500)), parse_units("1.5e3", 0));
+	assert_eq!(Ok(U256::from(1_500_000_000)), parse_units("1.5E3", 6));
+	assert_eq!(Ok(U256::from(15)), parse_units("150e-1", 0));
+	assert_eq!(Err(ERC20Error::UnexpectedType), parse_units("1.23e1", 0));
+	assert_eq!(Err(ERC20Error::UnexpectedType), parse_units("1e", 0));
+	assert_eq!(Err(ERC20Error::Overflow), parse_units("1e78", 0));
+}